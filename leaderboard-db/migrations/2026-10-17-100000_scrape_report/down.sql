@@ -0,0 +1,2 @@
+ALTER TABLE
+    leaderboard_scrape DROP COLUMN report;
\ No newline at end of file
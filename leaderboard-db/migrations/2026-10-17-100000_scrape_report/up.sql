@@ -0,0 +1,2 @@
+ALTER TABLE
+    leaderboard_scrape ADD COLUMN report JSONB;
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP TABLE leaderboard_page;
+
+DROP TABLE page_archive;
@@ -0,0 +1,13 @@
+CREATE TABLE page_archive (
+    hash VARCHAR PRIMARY KEY,
+    content BYTEA NOT NULL
+);
+
+CREATE TABLE leaderboard_page (
+    leaderboard_scrape_id INT NOT NULL,
+    page INT NOT NULL,
+    page_archive_hash VARCHAR NOT NULL,
+    CONSTRAINT pk_leaderboard_page PRIMARY KEY (leaderboard_scrape_id, page),
+    CONSTRAINT fk_scrape FOREIGN KEY(leaderboard_scrape_id) REFERENCES leaderboard_scrape(id),
+    CONSTRAINT fk_page_archive FOREIGN KEY(page_archive_hash) REFERENCES page_archive(hash)
+);
@@ -0,0 +1,3 @@
+-- Decoded names cannot be told apart from names that were never escaped, so this data
+-- repair is not reverted.
+SELECT 1;
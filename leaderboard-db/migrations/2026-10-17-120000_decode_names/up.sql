@@ -0,0 +1,58 @@
+-- Names were scraped from inner HTML, so entities the serializer escapes were stored
+-- escaped. Decode them, trim and normalize to NFC as the scraper now does.
+CREATE FUNCTION pg_temp.clean_name(name VARCHAR) RETURNS VARCHAR AS $$
+SELECT
+    normalize(
+        btrim(
+            replace(
+                replace(
+                    replace(replace(name, '&lt;', '<'), '&gt;', '>'),
+                    '&nbsp;',
+                    chr(160)
+                ),
+                '&amp;',
+                '&'
+            ),
+            E' \t\r\n' || chr(160)
+        ),
+        NFC
+    )
+$$ LANGUAGE SQL IMMUTABLE;
+
+UPDATE
+    leaderboard
+SET
+    name = pg_temp.clean_name(name)
+WHERE
+    name <> pg_temp.clean_name(name);
+
+-- Names that only differed in escaping or composition collapse into the oldest row.
+CREATE TEMPORARY TABLE names_merge ON COMMIT DROP AS
+SELECT
+    id,
+    MIN(id) OVER (PARTITION BY pg_temp.clean_name(name)) AS keep_id
+FROM
+    names;
+
+UPDATE
+    steam_association
+SET
+    names_id = names_merge.keep_id
+FROM
+    names_merge
+WHERE
+    steam_association.names_id = names_merge.id
+    AND names_merge.id <> names_merge.keep_id;
+
+DELETE FROM
+    names USING names_merge
+WHERE
+    names.id = names_merge.id
+    AND names_merge.id <> names_merge.keep_id;
+
+UPDATE
+    names
+SET
+    name = pg_temp.clean_name(name)
+WHERE
+    name <> pg_temp.clean_name(name);
@@ -0,0 +1 @@
+DROP TABLE association_review;
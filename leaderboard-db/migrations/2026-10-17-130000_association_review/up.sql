@@ -0,0 +1,9 @@
+CREATE TABLE association_review (
+    names_id INT NOT NULL,
+    avatar_hash_id INT NOT NULL,
+    candidates JSONB NOT NULL,
+    at TIMESTAMP NOT NULL,
+    CONSTRAINT pk_association_review PRIMARY KEY (names_id, avatar_hash_id),
+    CONSTRAINT fk_name FOREIGN KEY (names_id) REFERENCES names(id),
+    CONSTRAINT fk_avatar_hash FOREIGN KEY (avatar_hash_id) REFERENCES avatar_hash(id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE
+    association_review DROP COLUMN ambiguous;
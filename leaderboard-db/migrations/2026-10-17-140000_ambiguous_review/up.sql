@@ -0,0 +1,2 @@
+ALTER TABLE
+    association_review ADD COLUMN ambiguous BOOLEAN NOT NULL DEFAULT FALSE;
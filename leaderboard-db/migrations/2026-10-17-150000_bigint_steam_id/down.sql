@@ -0,0 +1,67 @@
+DROP VIEW current_leaderboard;
+
+DROP VIEW leaderboard_view;
+
+ALTER TABLE
+    steam_association ALTER COLUMN steam_id TYPE BYTEA USING decode(
+        substr(lpad(to_hex(steam_id), 16, '0'), 15, 2)
+        || substr(lpad(to_hex(steam_id), 16, '0'), 13, 2)
+        || substr(lpad(to_hex(steam_id), 16, '0'), 11, 2)
+        || substr(lpad(to_hex(steam_id), 16, '0'), 9, 2)
+        || substr(lpad(to_hex(steam_id), 16, '0'), 7, 2)
+        || substr(lpad(to_hex(steam_id), 16, '0'), 5, 2)
+        || substr(lpad(to_hex(steam_id), 16, '0'), 3, 2)
+        || substr(lpad(to_hex(steam_id), 16, '0'), 1, 2),
+        'hex'
+    );
+
+CREATE VIEW leaderboard_view AS
+SELECT
+    leaderboard.id,
+    leaderboard_scrape_id,
+    at,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    steam_id
+FROM
+    leaderboard
+    INNER JOIN leaderboard_scrape ON leaderboard.leaderboard_scrape_id = leaderboard_scrape.id
+    LEFT JOIN associated_leaderboard ON leaderboard.id = associated_leaderboard.leaderboard_id
+    LEFT JOIN steam_association ON steam_association.id = associated_leaderboard.steam_association_id
+ORDER BY
+    at DESC,
+    rank;
+
+CREATE VIEW current_leaderboard AS
+SELECT
+    leaderboard.id,
+    at,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    steam_id
+FROM
+    leaderboard
+    INNER JOIN leaderboard_scrape ON leaderboard.leaderboard_scrape_id = leaderboard_scrape.id
+    LEFT JOIN associated_leaderboard ON leaderboard.id = associated_leaderboard.leaderboard_id
+    LEFT JOIN steam_association ON steam_association.id = associated_leaderboard.steam_association_id
+WHERE
+    leaderboard_scrape_id = (
+        SELECT
+            id
+        FROM
+            leaderboard_scrape
+        ORDER BY
+            at DESC
+        LIMIT
+            1 OFFSET 1
+    )
+ORDER BY
+    rank;
@@ -0,0 +1,67 @@
+DROP VIEW current_leaderboard;
+
+DROP VIEW leaderboard_view;
+
+-- Individual SteamID64s stay below 2^63, so BIGINT holds them without NUMERIC.
+ALTER TABLE
+    steam_association ALTER COLUMN steam_id TYPE BIGINT USING (
+        get_byte(steam_id, 0)::BIGINT
+        | (get_byte(steam_id, 1)::BIGINT << 8)
+        | (get_byte(steam_id, 2)::BIGINT << 16)
+        | (get_byte(steam_id, 3)::BIGINT << 24)
+        | (get_byte(steam_id, 4)::BIGINT << 32)
+        | (get_byte(steam_id, 5)::BIGINT << 40)
+        | (get_byte(steam_id, 6)::BIGINT << 48)
+        | (get_byte(steam_id, 7)::BIGINT << 56)
+    );
+
+CREATE VIEW leaderboard_view AS
+SELECT
+    leaderboard.id,
+    leaderboard_scrape_id,
+    at,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    steam_id
+FROM
+    leaderboard
+    INNER JOIN leaderboard_scrape ON leaderboard.leaderboard_scrape_id = leaderboard_scrape.id
+    LEFT JOIN associated_leaderboard ON leaderboard.id = associated_leaderboard.leaderboard_id
+    LEFT JOIN steam_association ON steam_association.id = associated_leaderboard.steam_association_id
+ORDER BY
+    at DESC,
+    rank;
+
+CREATE VIEW current_leaderboard AS
+SELECT
+    leaderboard.id,
+    at,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    steam_id
+FROM
+    leaderboard
+    INNER JOIN leaderboard_scrape ON leaderboard.leaderboard_scrape_id = leaderboard_scrape.id
+    LEFT JOIN associated_leaderboard ON leaderboard.id = associated_leaderboard.leaderboard_id
+    LEFT JOIN steam_association ON steam_association.id = associated_leaderboard.steam_association_id
+WHERE
+    leaderboard_scrape_id = (
+        SELECT
+            id
+        FROM
+            leaderboard_scrape
+        ORDER BY
+            at DESC
+        LIMIT
+            1 OFFSET 1
+    )
+ORDER BY
+    rank;
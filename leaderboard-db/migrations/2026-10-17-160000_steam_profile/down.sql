@@ -0,0 +1 @@
+DROP TABLE steam_profile;
@@ -0,0 +1,11 @@
+CREATE TABLE steam_profile (
+    steam_id BIGINT PRIMARY KEY,
+    persona_name VARCHAR NOT NULL,
+    avatar_url VARCHAR NOT NULL,
+    profile_url VARCHAR NOT NULL,
+    country_code VARCHAR,
+    visibility INT NOT NULL,
+    synced_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX steam_profile_synced_at_index ON steam_profile (synced_at);
@@ -0,0 +1 @@
+DROP TABLE steam_vanity_url;
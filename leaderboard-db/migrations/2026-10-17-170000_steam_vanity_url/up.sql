@@ -0,0 +1,5 @@
+CREATE TABLE steam_vanity_url (
+    vanity_url VARCHAR PRIMARY KEY,
+    steam_id BIGINT NOT NULL,
+    resolved_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE playtime (
+    steam_id BIGINT NOT NULL,
+    at TIMESTAMP NOT NULL,
+    minutes INT NOT NULL,
+    CONSTRAINT pk_playtime PRIMARY KEY (steam_id, at)
+);
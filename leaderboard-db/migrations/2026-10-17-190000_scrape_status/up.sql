@@ -0,0 +1,86 @@
+DROP VIEW current_leaderboard;
+
+DROP VIEW leaderboard_view;
+
+-- Scrapes are now written in one transaction, so only a complete scrape has entries.
+ALTER TABLE
+    leaderboard_scrape
+ADD
+    COLUMN status VARCHAR NOT NULL DEFAULT 'complete' CONSTRAINT ck_scrape_status CHECK (status IN ('running', 'complete', 'failed'));
+
+UPDATE
+    leaderboard_scrape
+SET
+    status = 'failed'
+WHERE
+    NOT EXISTS (
+        SELECT
+        FROM
+            leaderboard
+        WHERE
+            leaderboard.leaderboard_scrape_id = leaderboard_scrape.id
+    );
+
+ALTER TABLE
+    leaderboard_scrape
+ALTER COLUMN
+    status
+SET
+    DEFAULT 'running';
+
+CREATE INDEX ix_leaderboard_scrape_status_at ON leaderboard_scrape (status, at);
+
+CREATE VIEW leaderboard_view AS
+SELECT
+    leaderboard.id,
+    leaderboard_scrape_id,
+    at,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    steam_id
+FROM
+    leaderboard
+    INNER JOIN leaderboard_scrape ON leaderboard.leaderboard_scrape_id = leaderboard_scrape.id
+    LEFT JOIN associated_leaderboard ON leaderboard.id = associated_leaderboard.leaderboard_id
+    LEFT JOIN steam_association ON steam_association.id = associated_leaderboard.steam_association_id
+WHERE
+    status = 'complete'
+ORDER BY
+    at DESC,
+    rank;
+
+CREATE VIEW current_leaderboard AS
+SELECT
+    leaderboard.id,
+    at,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    steam_id
+FROM
+    leaderboard
+    INNER JOIN leaderboard_scrape ON leaderboard.leaderboard_scrape_id = leaderboard_scrape.id
+    LEFT JOIN associated_leaderboard ON leaderboard.id = associated_leaderboard.leaderboard_id
+    LEFT JOIN steam_association ON steam_association.id = associated_leaderboard.steam_association_id
+WHERE
+    leaderboard_scrape_id = (
+        SELECT
+            id
+        FROM
+            leaderboard_scrape
+        WHERE
+            status = 'complete'
+        ORDER BY
+            at DESC
+        LIMIT
+            1
+    )
+ORDER BY
+    rank;
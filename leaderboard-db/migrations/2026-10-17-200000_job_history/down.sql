@@ -0,0 +1,11 @@
+DROP TABLE association_job;
+
+ALTER TABLE
+    leaderboard_scrape DROP COLUMN finished_at,
+    DROP COLUMN page_count,
+    DROP COLUMN entry_count,
+    DROP COLUMN http_statuses,
+    DROP COLUMN parse_errors,
+    DROP COLUMN scraper_version,
+    DROP COLUMN source,
+    DROP COLUMN error;
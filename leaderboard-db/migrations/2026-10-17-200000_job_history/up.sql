@@ -0,0 +1,57 @@
+ALTER TABLE
+    leaderboard_scrape
+ADD
+    COLUMN finished_at TIMESTAMP NULL,
+ADD
+    COLUMN page_count INT NULL,
+ADD
+    COLUMN entry_count INT NULL,
+ADD
+    COLUMN http_statuses JSONB NULL,
+ADD
+    COLUMN parse_errors INT NULL,
+ADD
+    COLUMN scraper_version VARCHAR NULL,
+ADD
+    COLUMN source VARCHAR NULL,
+ADD
+    COLUMN error VARCHAR NULL;
+
+-- Earlier scrapes only kept their report; their counts can be recovered from it.
+UPDATE
+    leaderboard_scrape
+SET
+    page_count = jsonb_array_length(report -> 'pages_seen'),
+    parse_errors = jsonb_array_length(report -> 'rows_skipped')
+WHERE
+    report IS NOT NULL;
+
+UPDATE
+    leaderboard_scrape
+SET
+    entry_count = (
+        SELECT
+            COUNT(*)
+        FROM
+            leaderboard
+        WHERE
+            leaderboard.leaderboard_scrape_id = leaderboard_scrape.id
+    );
+
+CREATE TABLE association_job (
+    id SERIAL PRIMARY KEY,
+    started_at TIMESTAMP NOT NULL,
+    finished_at TIMESTAMP NULL,
+    status VARCHAR NOT NULL DEFAULT 'running' CONSTRAINT ck_association_job_status CHECK (status IN ('running', 'complete', 'failed')),
+    scraper_version VARCHAR NOT NULL,
+    names_indexed INT NOT NULL DEFAULT 0,
+    avatars_hashed INT NOT NULL DEFAULT 0,
+    players_found INT NOT NULL DEFAULT 0,
+    players_associated INT NOT NULL DEFAULT 0,
+    players_queued INT NOT NULL DEFAULT 0,
+    lookup_failures INT NOT NULL DEFAULT 0,
+    entries_associated INT NOT NULL DEFAULT 0,
+    error VARCHAR NULL
+);
+
+CREATE INDEX ix_association_job_started_at ON association_job (started_at);
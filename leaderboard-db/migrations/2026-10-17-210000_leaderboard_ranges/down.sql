@@ -0,0 +1,108 @@
+DROP VIEW current_leaderboard;
+
+DROP VIEW leaderboard_view;
+
+-- Expand every range back into one row per complete scrape, with its associations.
+ALTER TABLE
+    leaderboard
+ADD
+    COLUMN expanded_from INT NULL;
+
+INSERT INTO
+    leaderboard (
+        leaderboard_scrape_id,
+        last_scrape_id,
+        rank,
+        avatar,
+        name,
+        rating,
+        wins,
+        losses,
+        expanded_from
+    )
+SELECT
+    leaderboard_scrape.id,
+    leaderboard_scrape.id,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    leaderboard.id
+FROM
+    leaderboard
+    INNER JOIN leaderboard_scrape ON leaderboard_scrape.id > leaderboard.leaderboard_scrape_id
+    AND leaderboard_scrape.id <= leaderboard.last_scrape_id
+WHERE
+    status = 'complete';
+
+INSERT INTO
+    associated_leaderboard
+SELECT
+    leaderboard.id,
+    associated_leaderboard.steam_association_id
+FROM
+    leaderboard
+    INNER JOIN associated_leaderboard ON leaderboard.expanded_from = associated_leaderboard.leaderboard_id;
+
+DROP INDEX leaderboard_scrape_range_index;
+
+ALTER TABLE
+    leaderboard DROP COLUMN expanded_from,
+    DROP COLUMN last_scrape_id;
+
+CREATE VIEW leaderboard_view AS
+SELECT
+    leaderboard.id,
+    leaderboard_scrape_id,
+    at,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    steam_id
+FROM
+    leaderboard
+    INNER JOIN leaderboard_scrape ON leaderboard.leaderboard_scrape_id = leaderboard_scrape.id
+    LEFT JOIN associated_leaderboard ON leaderboard.id = associated_leaderboard.leaderboard_id
+    LEFT JOIN steam_association ON steam_association.id = associated_leaderboard.steam_association_id
+WHERE
+    status = 'complete'
+ORDER BY
+    at DESC,
+    rank;
+
+CREATE VIEW current_leaderboard AS
+SELECT
+    leaderboard.id,
+    at,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    steam_id
+FROM
+    leaderboard
+    INNER JOIN leaderboard_scrape ON leaderboard.leaderboard_scrape_id = leaderboard_scrape.id
+    LEFT JOIN associated_leaderboard ON leaderboard.id = associated_leaderboard.leaderboard_id
+    LEFT JOIN steam_association ON steam_association.id = associated_leaderboard.steam_association_id
+WHERE
+    leaderboard_scrape_id = (
+        SELECT
+            id
+        FROM
+            leaderboard_scrape
+        WHERE
+            status = 'complete'
+        ORDER BY
+            at DESC
+        LIMIT
+            1
+    )
+ORDER BY
+    rank;
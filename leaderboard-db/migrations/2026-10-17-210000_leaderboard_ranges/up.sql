@@ -0,0 +1,160 @@
+DROP VIEW current_leaderboard;
+
+DROP VIEW leaderboard_view;
+
+-- A row now holds a player's standing in every complete scrape from leaderboard_scrape_id
+-- through last_scrape_id, so unchanged standings are stored once.
+ALTER TABLE
+    leaderboard
+ADD
+    COLUMN last_scrape_id INT NULL;
+
+UPDATE
+    leaderboard
+SET
+    last_scrape_id = leaderboard_scrape_id;
+
+ALTER TABLE
+    leaderboard
+ALTER COLUMN
+    last_scrape_id
+SET
+    NOT NULL,
+ADD
+    CONSTRAINT ck_scrape_range CHECK (last_scrape_id >= leaderboard_scrape_id);
+
+CREATE INDEX leaderboard_scrape_range_index ON leaderboard (last_scrape_id, leaderboard_scrape_id);
+
+-- Rows with the same standing in consecutive complete scrapes form an island, which is
+-- compacted into its first row.
+CREATE TEMPORARY TABLE leaderboard_island ON COMMIT DROP AS WITH sequenced AS (
+    SELECT
+        id,
+        ROW_NUMBER() OVER (
+            ORDER BY
+                id
+        ) AS seq
+    FROM
+        leaderboard_scrape
+    WHERE
+        status = 'complete'
+),
+numbered AS (
+    SELECT
+        leaderboard.id,
+        leaderboard_scrape_id,
+        rank,
+        avatar,
+        name,
+        rating,
+        wins,
+        losses,
+        seq - ROW_NUMBER() OVER (
+            PARTITION BY rank,
+            avatar,
+            name,
+            rating,
+            wins,
+            losses
+            ORDER BY
+                seq
+        ) AS island
+    FROM
+        leaderboard
+        INNER JOIN sequenced ON leaderboard.leaderboard_scrape_id = sequenced.id
+)
+SELECT
+    id,
+    FIRST_VALUE(id) OVER island AS keep_id,
+    LAST_VALUE(leaderboard_scrape_id) OVER island AS last_scrape_id
+FROM
+    numbered WINDOW island AS (
+        PARTITION BY rank,
+        avatar,
+        name,
+        rating,
+        wins,
+        losses,
+        island
+        ORDER BY
+            leaderboard_scrape_id ROWS BETWEEN UNBOUNDED PRECEDING
+            AND UNBOUNDED FOLLOWING
+    );
+
+UPDATE
+    leaderboard
+SET
+    last_scrape_id = leaderboard_island.last_scrape_id
+FROM
+    leaderboard_island
+WHERE
+    leaderboard.id = leaderboard_island.id
+    AND leaderboard_island.id = leaderboard_island.keep_id;
+
+DELETE FROM
+    associated_leaderboard USING leaderboard_island
+WHERE
+    associated_leaderboard.leaderboard_id = leaderboard_island.id
+    AND leaderboard_island.id <> leaderboard_island.keep_id;
+
+DELETE FROM
+    leaderboard USING leaderboard_island
+WHERE
+    leaderboard.id = leaderboard_island.id
+    AND leaderboard_island.id <> leaderboard_island.keep_id;
+
+CREATE VIEW leaderboard_view AS
+SELECT
+    leaderboard.id,
+    leaderboard_scrape.id AS leaderboard_scrape_id,
+    at,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    steam_id
+FROM
+    leaderboard
+    INNER JOIN leaderboard_scrape ON leaderboard_scrape.id BETWEEN leaderboard.leaderboard_scrape_id
+    AND leaderboard.last_scrape_id
+    LEFT JOIN associated_leaderboard ON leaderboard.id = associated_leaderboard.leaderboard_id
+    LEFT JOIN steam_association ON steam_association.id = associated_leaderboard.steam_association_id
+WHERE
+    status = 'complete'
+ORDER BY
+    at DESC,
+    rank;
+
+CREATE VIEW current_leaderboard AS WITH latest AS (
+    SELECT
+        id,
+        at
+    FROM
+        leaderboard_scrape
+    WHERE
+        status = 'complete'
+    ORDER BY
+        at DESC
+    LIMIT
+        1
+)
+SELECT
+    leaderboard.id,
+    latest.at,
+    rank,
+    avatar,
+    name,
+    rating,
+    wins,
+    losses,
+    steam_id
+FROM
+    leaderboard
+    INNER JOIN latest ON latest.id BETWEEN leaderboard.leaderboard_scrape_id
+    AND leaderboard.last_scrape_id
+    LEFT JOIN associated_leaderboard ON leaderboard.id = associated_leaderboard.leaderboard_id
+    LEFT JOIN steam_association ON steam_association.id = associated_leaderboard.steam_association_id
+ORDER BY
+    rank;
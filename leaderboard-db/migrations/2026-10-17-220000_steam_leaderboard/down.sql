@@ -0,0 +1,3 @@
+DROP TABLE steam_leaderboard_entry;
+
+DROP TABLE steam_leaderboard_scrape;
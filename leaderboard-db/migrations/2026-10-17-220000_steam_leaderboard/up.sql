@@ -0,0 +1,28 @@
+-- Steam leaderboard scrapes are kept apart from the HTML scrapes, whose complete runs make
+-- up the leaderboard history.
+CREATE TABLE steam_leaderboard_scrape (
+    id SERIAL PRIMARY KEY,
+    at TIMESTAMP NOT NULL,
+    finished_at TIMESTAMP NULL,
+    status VARCHAR NOT NULL DEFAULT 'running' CONSTRAINT ck_steam_leaderboard_scrape_status CHECK (status IN ('running', 'complete', 'failed')),
+    scraper_version VARCHAR NOT NULL,
+    page_count INT NULL,
+    entry_count INT NULL,
+    http_statuses JSONB NULL,
+    parse_errors INT NULL,
+    report JSONB NULL,
+    error VARCHAR NULL
+);
+
+CREATE INDEX ix_steam_leaderboard_scrape_at ON steam_leaderboard_scrape (at);
+
+CREATE TABLE steam_leaderboard_entry (
+    steam_leaderboard_scrape_id INT NOT NULL,
+    rank INT NOT NULL,
+    steam_id BIGINT NOT NULL,
+    avatar VARCHAR NOT NULL,
+    name VARCHAR NOT NULL,
+    rating FLOAT8 NOT NULL,
+    PRIMARY KEY (steam_leaderboard_scrape_id, steam_id),
+    CONSTRAINT fk_steam_leaderboard_scrape FOREIGN KEY (steam_leaderboard_scrape_id) REFERENCES steam_leaderboard_scrape (id) ON DELETE CASCADE
+);
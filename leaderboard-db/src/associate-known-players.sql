@@ -0,0 +1,23 @@
+WITH known AS (
+    SELECT
+        DISTINCT names.id AS names_id,
+        avatar_hash.id AS avatar_hash_id,
+        known.steam_id
+    FROM
+        unnest($1::VARCHAR[], $2::VARCHAR[], $3::BIGINT[]) AS known(name, avatar_hash, steam_id)
+        INNER JOIN names ON known.name = names.name
+        INNER JOIN avatar_hash ON known.avatar_hash = avatar_hash.hash
+)
+INSERT INTO
+    steam_association(names_id, avatar_hash_id, steam_id)
+SELECT
+    known.names_id,
+    known.avatar_hash_id,
+    known.steam_id
+FROM
+    known
+    LEFT JOIN steam_association ON known.names_id = steam_association.names_id
+    AND known.avatar_hash_id = steam_association.avatar_hash_id
+    AND known.steam_id = steam_association.steam_id
+WHERE
+    steam_association.id IS NULL;
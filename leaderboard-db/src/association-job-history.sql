@@ -0,0 +1,25 @@
+SELECT
+    id,
+    started_at,
+    finished_at,
+    EXTRACT(
+        EPOCH
+        FROM
+            finished_at - started_at
+    )::FLOAT8,
+    status,
+    scraper_version,
+    names_indexed,
+    avatars_hashed,
+    players_found,
+    players_associated,
+    players_queued,
+    lookup_failures,
+    entries_associated,
+    error
+FROM
+    association_job
+ORDER BY
+    started_at DESC
+LIMIT
+    $1;
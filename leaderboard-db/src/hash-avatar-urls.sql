@@ -14,14 +14,28 @@ hash AS (
     SELECT
         map.hash
     FROM
-        map ON CONFLICT DO NOTHING
+        map ON CONFLICT DO NOTHING RETURNING id,
+        hash
+),
+known AS (
+    SELECT
+        id,
+        hash
+    FROM
+        hash
+    UNION
+    SELECT
+        id,
+        hash
+    FROM
+        avatar_hash
 )
 INSERT INTO
     avatar_map(url, avatar_hash_id) (
         SELECT
             map.url,
-            avatar_hash.id
+            known.id
         FROM
             map
-            INNER JOIN avatar_hash ON map.hash = avatar_hash.hash
+            INNER JOIN known ON map.hash = known.hash
     ) ON CONFLICT DO NOTHING;
\ No newline at end of file
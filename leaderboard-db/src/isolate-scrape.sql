@@ -0,0 +1,76 @@
+WITH spanning AS (
+    SELECT
+        leaderboard.*,
+        nextval(pg_get_serial_sequence('leaderboard', 'id')) AS following_id
+    FROM
+        leaderboard
+    WHERE
+        leaderboard_scrape_id < $1
+        AND last_scrape_id > $1
+),
+following AS (
+    INSERT INTO
+        leaderboard (
+            id,
+            leaderboard_scrape_id,
+            last_scrape_id,
+            rank,
+            avatar,
+            name,
+            rating,
+            wins,
+            losses
+        )
+    SELECT
+        following_id,
+        (
+            SELECT
+                MIN(id)
+            FROM
+                leaderboard_scrape
+            WHERE
+                id > $1
+        ),
+        last_scrape_id,
+        rank,
+        avatar,
+        name,
+        rating,
+        wins,
+        losses
+    FROM
+        spanning
+),
+associated AS (
+    INSERT INTO
+        associated_leaderboard (leaderboard_id, steam_association_id)
+    SELECT
+        spanning.following_id,
+        associated_leaderboard.steam_association_id
+    FROM
+        spanning
+        INNER JOIN associated_leaderboard ON associated_leaderboard.leaderboard_id = spanning.id
+),
+later AS (
+    UPDATE
+        leaderboard
+    SET
+        leaderboard_scrape_id = (
+            SELECT
+                MIN(id)
+            FROM
+                leaderboard_scrape
+            WHERE
+                id > $1
+        )
+    WHERE
+        leaderboard_scrape_id = $1
+        AND last_scrape_id > $1
+)
+UPDATE
+    leaderboard
+SET
+    last_scrape_id = $1 - 1
+WHERE
+    leaderboard_scrape_id < $1
+    AND last_scrape_id >= $1;
@@ -10,6 +10,8 @@ WITH lag_games AS (
         ) AS lag_games
     FROM
         leaderboard_view
+    WHERE
+        steam_id IS NOT NULL
     ORDER BY
         at DESC
 ),
@@ -2,35 +2,83 @@
 extern crate diesel;
 
 use diesel::{
+    sql_types::{Array, BigInt, Integer, VarChar},
     BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl, PgConnection, QueryDsl,
     RunQueryDsl,
 };
-use models::{NewEntry, NewLeaderboardScrape, NewSteamAssociation};
-use std::{env::VarError, time::SystemTime};
-use tokio::sync::AcquireError;
+use models::{
+    AssociationJob, AssociationJobRun, AssociationReview, JobStatus, LeaderboardPage,
+    NewAssociationJob, NewEntry, NewLeaderboardScrape, NewSteamAssociation,
+    NewSteamLeaderboardEntry, NewSteamLeaderboardScrape, PageArchive, Playtime, ScrapeId,
+    ScrapeParse, ScrapeRun, SteamProfile, VanityUrl,
+};
+use retention::PruneReport;
+use std::{collections::HashSet, env::VarError, fmt, str::FromStr, time::SystemTime};
+use steam_id::SteamId;
+use writer::ScrapeWriter;
 
+// diesel 1.4's derives and `table!` expand to impls inside anonymous consts.
+#[allow(non_local_definitions)]
 pub mod models;
+pub mod retention;
+#[allow(non_local_definitions)]
 pub mod schema;
 pub mod service;
+pub mod writer;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Database connection error: {0}")]
     ConnectionError(#[from] diesel::result::ConnectionError),
     #[error("Database connection pool error: {0}")]
-    ConnectionPoolError(#[from] r2d2::Error),
+    ConnectionPoolError(#[from] deadpool_postgres::PoolError),
+    #[error("Database connection pool setup error: {0}")]
+    PoolBuildError(#[from] deadpool_postgres::BuildError),
+    #[error("Postgres error: {0}")]
+    PostgresError(#[from] tokio_postgres::Error),
     #[error("Databse query error: {0}")]
     QueryError(#[from] diesel::result::Error),
-    #[error("Oneshot receive error: {0}")]
-    OneshotReceiverError(#[from] tokio::sync::oneshot::error::RecvError),
-    #[error("Semaphore closed")]
-    SemaphoreError(#[from] AcquireError),
+    #[error("invalid retention policy: {0}")]
+    InvalidRetentionPolicy(String),
+    #[error("unknown storage mode {0:?}")]
+    UnknownStorageMode(String),
     #[error("DATABASE_URL must be set")]
     UrlNotSet(#[from] VarError),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// How `write_scrape` writes a scrape's entries.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StorageMode {
+    /// Writes a row for every entry.
+    Full,
+    /// Merges the rows whose standing is unchanged into the ranges of the neighbouring
+    /// complete scrapes, so only the entries that changed add rows.
+    Delta,
+}
+
+impl FromStr for StorageMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "full" => Ok(Self::Full),
+            "delta" => Ok(Self::Delta),
+            _ => Err(Error::UnknownStorageMode(value.into())),
+        }
+    }
+}
+
+impl fmt::Display for StorageMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => f.write_str("full"),
+            Self::Delta => f.write_str("delta"),
+        }
+    }
+}
+
 pub struct LeaderboardDatabase {
     connection: PgConnection,
 }
@@ -43,9 +91,18 @@ impl LeaderboardDatabase {
         Ok(Self { connection })
     }
 
-    pub fn start_scrape(&self) -> Result<models::LeaderboardScrape> {
+    /// Records a running scrape, which stays out of the leaderboard views until its
+    /// `ScrapeWriter` completes.
+    pub fn start_scrape(
+        &self,
+        source: &str,
+        scraper_version: &str,
+    ) -> Result<models::LeaderboardScrape> {
         let record = NewLeaderboardScrape {
             at: SystemTime::now(),
+            status: JobStatus::Running,
+            scraper_version,
+            source,
         };
 
         let result = diesel::insert_into(schema::leaderboard_scrape::table)
@@ -59,13 +116,329 @@ impl LeaderboardDatabase {
         Ok(result)
     }
 
+    /// Opens a transaction that writes a scrape's pages and entries as they arrive, so a
+    /// scrape never has to hold the whole leaderboard in memory.
+    pub fn write_scrape(&self, scrape_id: i32, storage: StorageMode) -> Result<ScrapeWriter<'_>> {
+        ScrapeWriter::begin(self, scrape_id, storage)
+    }
+
+    /// Writes a scrape's archived pages, entries and run metadata in one transaction and
+    /// marks it complete. Returns the number of rows left for the scrape, which in delta
+    /// mode is the number of entries that changed.
+    pub fn complete_scrape(
+        &self,
+        scrape_id: i32,
+        pages: &[(i32, PageArchive)],
+        records: &[NewEntry],
+        run: &ScrapeRun,
+        storage: StorageMode,
+    ) -> Result<usize> {
+        let mut writer = self.write_scrape(scrape_id, storage)?;
+
+        for (page, archive) in pages {
+            writer.archive_page(*page, archive)?;
+        }
+
+        writer.write_entries(records)?;
+        writer.complete(run)
+    }
+
+    /// Marks a scrape failed and records its run metadata. None of its pages or entries are
+    /// stored.
+    pub fn fail_scrape(&self, scrape_id: i32, run: &ScrapeRun) -> Result<usize> {
+        self.finish_scrape(scrape_id, JobStatus::Failed, 0, run)
+    }
+
+    fn finish_scrape(
+        &self,
+        scrape_id: i32,
+        status: JobStatus,
+        entry_count: usize,
+        run: &ScrapeRun,
+    ) -> Result<usize> {
+        use schema::leaderboard_scrape;
+
+        diesel::update(leaderboard_scrape::table.find(scrape_id))
+            .set((
+                leaderboard_scrape::status.eq(status),
+                leaderboard_scrape::finished_at.eq(SystemTime::now()),
+                leaderboard_scrape::entry_count.eq(entry_count as i32),
+                run,
+            ))
+            .execute(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Starts a scrape of the Steam leaderboard. Steam scrapes are kept apart from the HTML
+    /// scrapes and never appear in the leaderboard views.
+    pub fn start_steam_scrape(
+        &self,
+        scraper_version: &str,
+    ) -> Result<models::SteamLeaderboardScrape> {
+        let record = NewSteamLeaderboardScrape {
+            at: SystemTime::now(),
+            status: JobStatus::Running,
+            scraper_version,
+        };
+
+        diesel::insert_into(schema::steam_leaderboard_scrape::table)
+            .values(&record)
+            .get_result(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Stores a Steam scrape's entries and run metadata in one transaction and marks it
+    /// complete. Returns the number of entries stored.
+    pub fn complete_steam_scrape(
+        &self,
+        scrape_id: i32,
+        records: &[NewSteamLeaderboardEntry],
+        run: &ScrapeRun,
+    ) -> Result<usize> {
+        const BATCH_SIZE: usize = 4096;
+
+        self.connection.transaction(|| {
+            let n = records.chunks(BATCH_SIZE).try_fold(0, |n, batch| {
+                diesel::insert_into(schema::steam_leaderboard_entry::table)
+                    .values(batch)
+                    .execute(&self.connection)
+                    .map(|count| n + count)
+            })?;
+
+            self.finish_steam_scrape(scrape_id, JobStatus::Complete, n, run)?;
+
+            Ok(n)
+        })
+    }
+
+    /// Marks a Steam scrape failed and records its run metadata.
+    pub fn fail_steam_scrape(&self, scrape_id: i32, run: &ScrapeRun) -> Result<usize> {
+        self.finish_steam_scrape(scrape_id, JobStatus::Failed, 0, run)
+    }
+
+    fn finish_steam_scrape(
+        &self,
+        scrape_id: i32,
+        status: JobStatus,
+        entry_count: usize,
+        run: &ScrapeRun,
+    ) -> Result<usize> {
+        use schema::steam_leaderboard_scrape;
+
+        diesel::update(steam_leaderboard_scrape::table.find(scrape_id))
+            .set((
+                steam_leaderboard_scrape::status.eq(status),
+                steam_leaderboard_scrape::finished_at.eq(SystemTime::now()),
+                steam_leaderboard_scrape::entry_count.eq(entry_count as i32),
+                steam_leaderboard_scrape::page_count.eq(run.page_count),
+                steam_leaderboard_scrape::http_statuses.eq(&run.http_statuses),
+                steam_leaderboard_scrape::parse_errors.eq(run.parse_errors),
+                steam_leaderboard_scrape::report.eq(&run.report),
+                steam_leaderboard_scrape::error.eq(&run.error),
+            ))
+            .execute(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Returns the name, avatar URL and Steam ID of every player in the latest complete
+    /// Steam scrape.
+    pub fn get_steam_leaderboard(&self) -> Result<Vec<(String, String, SteamId)>> {
+        use schema::{steam_leaderboard_entry, steam_leaderboard_scrape};
+
+        let latest = steam_leaderboard_scrape::table
+            .filter(steam_leaderboard_scrape::status.eq(JobStatus::Complete))
+            .order(steam_leaderboard_scrape::id.desc())
+            .select(steam_leaderboard_scrape::id)
+            .limit(1);
+
+        steam_leaderboard_entry::table
+            .filter(steam_leaderboard_entry::steam_leaderboard_scrape_id.eq_any(latest))
+            .order(steam_leaderboard_entry::rank)
+            .select((
+                steam_leaderboard_entry::name,
+                steam_leaderboard_entry::avatar,
+                steam_leaderboard_entry::steam_id,
+            ))
+            .load(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Inserts records in batches that stay below the Postgres bind parameter limit.
     pub fn store_entries(&self, records: &[NewEntry]) -> Result<usize> {
-        diesel::insert_into(schema::leaderboard::table)
-            .values(records)
+        const BATCH_SIZE: usize = 4096;
+
+        records.chunks(BATCH_SIZE).try_fold(0, |n, batch| {
+            diesel::insert_into(schema::leaderboard::table)
+                .values(batch)
+                .execute(&self.connection)
+                .map(|count| n + count)
+                .map_err(Error::from)
+        })
+    }
+
+    pub fn store_report(&self, scrape_id: i32, parse: &ScrapeParse) -> Result<usize> {
+        use schema::leaderboard_scrape;
+
+        diesel::update(leaderboard_scrape::table.find(scrape_id))
+            .set(parse)
             .execute(&self.connection)
             .map_err(Error::from)
     }
 
+    /// Stores a compressed page once per content hash and links it to its scrape.
+    pub fn archive_page(&self, scrape_id: i32, page: i32, archive: &PageArchive) -> Result<usize> {
+        self.connection.transaction(|| {
+            diesel::insert_into(schema::page_archive::table)
+                .values(archive)
+                .on_conflict_do_nothing()
+                .execute(&self.connection)?;
+
+            diesel::insert_into(schema::leaderboard_page::table)
+                .values(&LeaderboardPage {
+                    leaderboard_scrape_id: scrape_id,
+                    page,
+                    page_archive_hash: archive.hash.clone(),
+                })
+                .execute(&self.connection)
+                .map_err(Error::from)
+        })
+    }
+
+    /// Returns the ids of every complete scrape with archived pages, oldest first.
+    pub fn get_archived_scrapes(&self) -> Result<Vec<i32>> {
+        use schema::{leaderboard_page, leaderboard_scrape};
+
+        leaderboard_page::table
+            .inner_join(leaderboard_scrape::table)
+            .filter(leaderboard_scrape::status.eq(JobStatus::Complete))
+            .select(leaderboard_page::leaderboard_scrape_id)
+            .distinct()
+            .order(leaderboard_page::leaderboard_scrape_id)
+            .load(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Returns the archived pages of a scrape in page order.
+    pub fn get_archived_pages(&self, scrape_id: i32) -> Result<Vec<(i32, PageArchive)>> {
+        use schema::{leaderboard_page, page_archive};
+
+        leaderboard_page::table
+            .inner_join(page_archive::table)
+            .filter(leaderboard_page::leaderboard_scrape_id.eq(scrape_id))
+            .order(leaderboard_page::page)
+            .select((
+                leaderboard_page::page,
+                (page_archive::hash, page_archive::content),
+            ))
+            .load(&self.connection)
+            .map_err(Error::from)
+    }
+
+    pub fn count_entries(&self, scrape_id: i32) -> Result<i64> {
+        use schema::leaderboard;
+
+        leaderboard::table
+            .filter(leaderboard::leaderboard_scrape_id.le(scrape_id))
+            .filter(leaderboard::last_scrape_id.ge(scrape_id))
+            .count()
+            .get_result(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Replaces the entries of a scrape, dropping their Steam associations, which
+    /// `associate_leaderboard` restores. Rows that also cover other scrapes are split first,
+    /// keeping their associations, and the new rows are merged back into the ranges of the
+    /// neighbouring complete scrapes where the standing is unchanged.
+    pub fn replace_entries(&self, scrape_id: i32, records: &[NewEntry]) -> Result<usize> {
+        self.connection.transaction(|| {
+            self.lock_ranges()?;
+            self.clear_entries(scrape_id)?;
+
+            let n = self.store_entries(records)?;
+
+            self.merge_ranges(scrape_id)?;
+
+            Ok(n)
+        })
+    }
+
+    /// Locks the scrapes until the end of the transaction, so only one transaction at a time
+    /// reads which scrapes are complete and changes ranges based on it.
+    fn lock_ranges(&self) -> Result<usize> {
+        diesel::sql_query("LOCK TABLE leaderboard_scrape IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Takes a scrape out of the ranges of rows that also cover other scrapes, splitting rows
+    /// that span it in two with copies of their associations. Only the rows covering the
+    /// scrape alone are left covering it.
+    fn isolate_scrape(&self, scrape_id: i32) -> Result<usize> {
+        diesel::sql_query(include_str!("isolate-scrape.sql"))
+            .bind::<Integer, _>(scrape_id)
+            .execute(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Deletes the rows of a scrape and their Steam associations, first taking it out of the
+    /// ranges of rows that also cover other scrapes.
+    fn clear_entries(&self, scrape_id: i32) -> Result<usize> {
+        use schema::{associated_leaderboard, leaderboard};
+
+        self.isolate_scrape(scrape_id)?;
+
+        let ids = leaderboard::table
+            .select(leaderboard::id)
+            .filter(leaderboard::leaderboard_scrape_id.eq(scrape_id));
+
+        diesel::delete(
+            associated_leaderboard::table
+                .filter(associated_leaderboard::leaderboard_id.eq_any(ids)),
+        )
+        .execute(&self.connection)?;
+        diesel::delete(leaderboard::table.filter(leaderboard::leaderboard_scrape_id.eq(scrape_id)))
+            .execute(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Merges the rows of a complete scrape covering it alone with the rows of the complete
+    /// scrapes before and after it whose standing is the same. A merged row keeps the earlier
+    /// association. Returns the number of the scrape's rows merged into earlier ranges.
+    fn merge_ranges(&self, scrape_id: i32) -> Result<usize> {
+        use diesel::OptionalExtension;
+        use schema::leaderboard_scrape;
+
+        let complete = leaderboard_scrape::table
+            .filter(leaderboard_scrape::status.eq(JobStatus::Complete))
+            .select(leaderboard_scrape::id);
+        let before: Option<i32> = complete
+            .filter(leaderboard_scrape::id.lt(scrape_id))
+            .order(leaderboard_scrape::id.desc())
+            .first(&self.connection)
+            .optional()?;
+        let after: Option<i32> = complete
+            .filter(leaderboard_scrape::id.gt(scrape_id))
+            .order(leaderboard_scrape::id)
+            .first(&self.connection)
+            .optional()?;
+        let merge = |earlier: i32, later: i32| {
+            diesel::sql_query(include_str!("merge-ranges.sql"))
+                .bind::<Integer, _>(earlier)
+                .bind::<Integer, _>(later)
+                .execute(&self.connection)
+                .map_err(Error::from)
+        };
+
+        if let Some(after) = after {
+            merge(scrape_id, after)?;
+        }
+
+        match before {
+            Some(before) => merge(before, scrape_id),
+            None => Ok(0),
+        }
+    }
+
     pub fn index_names(&self) -> Result<usize> {
         let new_names = schema::leaderboard::table
             .select(schema::leaderboard::name)
@@ -88,6 +461,17 @@ impl LeaderboardDatabase {
             .map_err(Error::from)
     }
 
+    /// Returns the hashes of avatars used under at least `min_names` different names.
+    pub fn get_shared_avatar_hashes(&self, min_names: i64) -> Result<Vec<String>> {
+        let sql = include_str!("shared-avatar-hashes.sql");
+
+        diesel::sql_query(sql)
+            .bind::<BigInt, _>(min_names)
+            .load::<models::SharedAvatarHash>(&self.connection)
+            .map(|rows| rows.into_iter().map(|row| row.hash).collect())
+            .map_err(Error::from)
+    }
+
     pub fn get_new_players(&self) -> Result<Vec<(String, String, i32, i32)>> {
         use schema::{avatar_hash, avatar_map, leaderboard, names, steam_association};
 
@@ -116,20 +500,252 @@ impl LeaderboardDatabase {
         &self,
         names_id: i32,
         avatar_hash_id: i32,
-        steam_id: Vec<u8>,
+        steam_id: SteamId,
     ) -> Result<usize> {
+        use schema::association_review;
+
         let record = NewSteamAssociation {
             names_id,
             avatar_hash_id,
             steam_id,
         };
 
-        diesel::insert_into(schema::steam_association::table)
+        self.connection.transaction(|| {
+            diesel::delete(association_review::table.find((names_id, avatar_hash_id)))
+                .execute(&self.connection)?;
+
+            diesel::insert_into(schema::steam_association::table)
+                .values(&record)
+                .execute(&self.connection)
+                .map_err(Error::from)
+        })
+    }
+
+    /// Associates players whose Steam ID is already known, such as those read from the Steam
+    /// leaderboard, with their name and avatar hash. Only names and avatars already seen on
+    /// the leaderboard are matched, so run after `index_names` and `hash_avatar_urls`.
+    pub fn associate_known_players(&self, players: &[(&str, &str, SteamId)]) -> Result<usize> {
+        let sql = include_str!("associate-known-players.sql");
+        let names: Vec<_> = players.iter().map(|(name, _, _)| *name).collect();
+        let avatars: Vec<_> = players.iter().map(|(_, avatar, _)| *avatar).collect();
+        let steam_ids: Vec<_> = players
+            .iter()
+            .map(|(_, _, steam_id)| steam_id.as_u64() as i64)
+            .collect();
+
+        diesel::sql_query(sql)
+            .bind::<Array<VarChar>, _>(names)
+            .bind::<Array<VarChar>, _>(avatars)
+            .bind::<Array<BigInt>, _>(steam_ids)
+            .execute(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Queues a player's candidate Steam accounts for review, replacing any earlier queue
+    /// entry for the same name and avatar.
+    pub fn queue_review(
+        &self,
+        names_id: i32,
+        avatar_hash_id: i32,
+        candidates: &serde_json::Value,
+        ambiguous: bool,
+    ) -> Result<usize> {
+        use diesel::pg::upsert::excluded;
+        use schema::association_review;
+
+        let record = AssociationReview {
+            names_id,
+            avatar_hash_id,
+            candidates: candidates.clone(),
+            at: SystemTime::now(),
+            ambiguous,
+        };
+
+        diesel::insert_into(association_review::table)
+            .values(&record)
+            .on_conflict((
+                association_review::names_id,
+                association_review::avatar_hash_id,
+            ))
+            .do_update()
+            .set((
+                association_review::candidates.eq(excluded(association_review::candidates)),
+                association_review::at.eq(excluded(association_review::at)),
+                association_review::ambiguous.eq(excluded(association_review::ambiguous)),
+            ))
+            .execute(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Returns the review queue with each player's name and avatar hash, oldest first.
+    pub fn get_review_queue(&self) -> Result<Vec<(String, String, AssociationReview)>> {
+        use schema::{association_review, avatar_hash, names};
+
+        association_review::table
+            .inner_join(names::table)
+            .inner_join(avatar_hash::table)
+            .order(association_review::at)
+            .select((
+                names::name,
+                avatar_hash::hash,
+                association_review::all_columns,
+            ))
+            .load(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Records a player's total Line War playtime as of now.
+    pub fn store_playtime(&self, steam_id: SteamId, minutes: i32) -> Result<usize> {
+        let record = Playtime {
+            steam_id,
+            at: SystemTime::now(),
+            minutes,
+        };
+
+        diesel::insert_into(schema::playtime::table)
             .values(&record)
             .execute(&self.connection)
             .map_err(Error::from)
     }
 
+    /// Returns the Steam ID a custom profile URL resolved to since `resolved_after`, if any.
+    /// Older resolutions are ignored because the URL may have passed to another account.
+    pub fn get_vanity_url(
+        &self,
+        vanity_url: &str,
+        resolved_after: SystemTime,
+    ) -> Result<Option<SteamId>> {
+        use diesel::OptionalExtension;
+        use schema::steam_vanity_url;
+
+        steam_vanity_url::table
+            .find(vanity_url)
+            .filter(steam_vanity_url::resolved_at.gt(resolved_after))
+            .select(steam_vanity_url::steam_id)
+            .first(&self.connection)
+            .optional()
+            .map_err(Error::from)
+    }
+
+    /// Records what a custom profile URL resolved to, replacing any earlier resolution.
+    pub fn store_vanity_url(&self, vanity_url: &str, steam_id: SteamId) -> Result<usize> {
+        use schema::steam_vanity_url;
+
+        let record = VanityUrl {
+            vanity_url: vanity_url.to_string(),
+            steam_id,
+            resolved_at: SystemTime::now(),
+        };
+
+        diesel::insert_into(steam_vanity_url::table)
+            .values(&record)
+            .on_conflict(steam_vanity_url::vanity_url)
+            .do_update()
+            .set((
+                steam_vanity_url::steam_id.eq(record.steam_id),
+                steam_vanity_url::resolved_at.eq(record.resolved_at),
+            ))
+            .execute(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Returns the associated Steam IDs without a profile synced since `synced_before`.
+    pub fn get_profiles_to_sync(&self, synced_before: SystemTime) -> Result<Vec<SteamId>> {
+        use schema::{steam_association, steam_profile};
+
+        steam_association::table
+            .left_join(
+                steam_profile::table.on(steam_association::steam_id.eq(steam_profile::steam_id)),
+            )
+            .filter(
+                steam_profile::synced_at
+                    .is_null()
+                    .or(steam_profile::synced_at.lt(synced_before)),
+            )
+            .select(steam_association::steam_id)
+            .distinct()
+            .load(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Inserts or refreshes Steam profiles.
+    pub fn store_profiles(&self, profiles: &[SteamProfile]) -> Result<usize> {
+        use diesel::pg::upsert::excluded;
+        use schema::steam_profile;
+
+        diesel::insert_into(steam_profile::table)
+            .values(profiles)
+            .on_conflict(steam_profile::steam_id)
+            .do_update()
+            .set((
+                steam_profile::persona_name.eq(excluded(steam_profile::persona_name)),
+                steam_profile::avatar_url.eq(excluded(steam_profile::avatar_url)),
+                steam_profile::profile_url.eq(excluded(steam_profile::profile_url)),
+                steam_profile::country_code.eq(excluded(steam_profile::country_code)),
+                steam_profile::visibility.eq(excluded(steam_profile::visibility)),
+                steam_profile::synced_at.eq(excluded(steam_profile::synced_at)),
+            ))
+            .execute(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Returns the name and Steam ID of associations whose profile Steam has not returned
+    /// since `synced_before`, which usually means the account was deleted or the
+    /// association is wrong.
+    pub fn get_stale_associations(
+        &self,
+        synced_before: SystemTime,
+    ) -> Result<Vec<(String, SteamId)>> {
+        use schema::{names, steam_association, steam_profile};
+
+        steam_association::table
+            .inner_join(names::table)
+            .left_join(
+                steam_profile::table.on(steam_association::steam_id.eq(steam_profile::steam_id)),
+            )
+            .filter(
+                steam_profile::synced_at
+                    .is_null()
+                    .or(steam_profile::synced_at.lt(synced_before)),
+            )
+            .select((names::name, steam_association::steam_id))
+            .order(names::name)
+            .load(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Records a running association job, which `finish_association_job` completes.
+    pub fn start_association_job(&self, scraper_version: &str) -> Result<AssociationJob> {
+        let record = NewAssociationJob {
+            started_at: SystemTime::now(),
+            status: JobStatus::Running,
+            scraper_version,
+        };
+
+        diesel::insert_into(schema::association_job::table)
+            .values(&record)
+            .get_result(&self.connection)
+            .map_err(Error::from)
+    }
+
+    pub fn finish_association_job(
+        &self,
+        job_id: i32,
+        status: JobStatus,
+        run: &AssociationJobRun,
+    ) -> Result<usize> {
+        use schema::association_job;
+
+        diesel::update(association_job::table.find(job_id))
+            .set((
+                association_job::status.eq(status),
+                association_job::finished_at.eq(SystemTime::now()),
+                run,
+            ))
+            .execute(&self.connection)
+            .map_err(Error::from)
+    }
+
     pub fn associate_leaderboard(&self) -> Result<usize> {
         let sql = include_str!("associate-leaderboard.sql");
 
@@ -137,4 +753,156 @@ impl LeaderboardDatabase {
             .execute(&self.connection)
             .map_err(Error::from)
     }
+
+    /// Returns the id, start time and status of every scrape, oldest first.
+    pub fn get_scrapes(&self) -> Result<Vec<(i32, SystemTime, JobStatus)>> {
+        use schema::leaderboard_scrape;
+
+        leaderboard_scrape::table
+            .select((
+                leaderboard_scrape::id,
+                leaderboard_scrape::at,
+                leaderboard_scrape::status,
+            ))
+            .order(leaderboard_scrape::at)
+            .load(&self.connection)
+            .map_err(Error::from)
+    }
+
+    /// Returns the scrapes holding a player's first or last appearance, or their best rank
+    /// or rating, with players told apart by Steam ID once associated and by name before.
+    pub fn get_protected_scrapes(&self) -> Result<HashSet<i32>> {
+        let sql = include_str!("protected-scrapes.sql");
+
+        diesel::sql_query(sql)
+            .load::<ScrapeId>(&self.connection)
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| row.leaderboard_scrape_id)
+                    .collect()
+            })
+            .map_err(Error::from)
+    }
+
+    /// Deletes scrapes with their archived pages in one transaction. Leaderboard rows are
+    /// trimmed to the complete scrapes left in their range, and rows left with none are
+    /// deleted along with their Steam associations.
+    pub fn prune_scrapes(&self, scrape_ids: &[i32]) -> Result<PruneReport> {
+        use schema::{leaderboard_page, leaderboard_scrape};
+
+        self.connection.transaction(|| {
+            diesel::sql_query(
+                "CREATE TEMPORARY TABLE pruned_range (id INT PRIMARY KEY, first_id INT NULL, \
+                 last_id INT NULL) ON COMMIT DROP",
+            )
+            .execute(&self.connection)?;
+            diesel::sql_query(include_str!("prune-ranges.sql"))
+                .bind::<Array<Integer>, _>(scrape_ids)
+                .execute(&self.connection)?;
+            diesel::sql_query(
+                "DELETE FROM associated_leaderboard USING pruned_range \
+                 WHERE leaderboard_id = pruned_range.id AND first_id IS NULL",
+            )
+            .execute(&self.connection)?;
+
+            let rows_removed = diesel::sql_query(
+                "DELETE FROM leaderboard USING pruned_range \
+                 WHERE leaderboard.id = pruned_range.id AND first_id IS NULL",
+            )
+            .execute(&self.connection)?;
+            let rows_trimmed = diesel::sql_query(
+                "UPDATE leaderboard SET leaderboard_scrape_id = first_id, last_scrape_id = last_id \
+                 FROM pruned_range WHERE leaderboard.id = pruned_range.id AND first_id IS NOT NULL",
+            )
+            .execute(&self.connection)?;
+            let pages_removed = diesel::delete(
+                leaderboard_page::table
+                    .filter(leaderboard_page::leaderboard_scrape_id.eq_any(scrape_ids)),
+            )
+            .execute(&self.connection)?;
+
+            diesel::sql_query(
+                "DELETE FROM page_archive WHERE NOT EXISTS \
+                 (SELECT FROM leaderboard_page WHERE page_archive_hash = hash)",
+            )
+            .execute(&self.connection)?;
+
+            let scrapes_removed = diesel::delete(
+                leaderboard_scrape::table.filter(leaderboard_scrape::id.eq_any(scrape_ids)),
+            )
+            .execute(&self.connection)?;
+
+            Ok(PruneReport {
+                scrapes_removed,
+                rows_removed,
+                rows_trimmed,
+                pages_removed,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        models::{NewSteamLeaderboardEntry, ScrapeRun},
+        schema,
+        writer::test::connect,
+    };
+    use diesel::{QueryDsl, RunQueryDsl};
+    use steam_id::SteamId;
+
+    #[test]
+    #[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+    fn test_steam_scrapes_stay_out_of_leaderboard() {
+        let db = connect();
+        let run = ScrapeRun {
+            page_count: 1,
+            http_statuses: serde_json::json!({}),
+            parse_errors: 0,
+            report: serde_json::json!({}),
+            error: None,
+        };
+        let steam_id = SteamId::new(76561198020520825).unwrap();
+        let views: i64 = schema::leaderboard_view::table
+            .count()
+            .get_result(&db.connection)
+            .unwrap();
+
+        for (name, failed) in [("steam-a", false), ("steam-b", true)] {
+            let scrape_id = db.start_steam_scrape("test").unwrap().id;
+
+            if failed {
+                db.fail_steam_scrape(scrape_id, &run).unwrap();
+                continue;
+            }
+
+            let record = NewSteamLeaderboardEntry {
+                steam_leaderboard_scrape_id: scrape_id,
+                rank: 1,
+                steam_id,
+                avatar: name,
+                name,
+                rating: 1500.0,
+            };
+
+            assert_eq!(
+                db.complete_steam_scrape(scrape_id, &[record], &run)
+                    .unwrap(),
+                1
+            );
+        }
+
+        assert_eq!(
+            db.get_steam_leaderboard().unwrap(),
+            vec![("steam-a".to_string(), "steam-a".to_string(), steam_id)]
+        );
+        assert_eq!(
+            schema::leaderboard_view::table
+                .count()
+                .get_result::<i64>(&db.connection)
+                .unwrap(),
+            views
+        );
+    }
 }
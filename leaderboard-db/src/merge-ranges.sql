@@ -0,0 +1,101 @@
+WITH earlier AS (
+    SELECT
+        id,
+        rank,
+        avatar,
+        name,
+        rating,
+        wins,
+        losses,
+        ROW_NUMBER() OVER standing AS n
+    FROM
+        leaderboard
+    WHERE
+        leaderboard_scrape_id <= $1
+        AND last_scrape_id >= $1
+        AND last_scrape_id < $2 WINDOW standing AS (
+            PARTITION BY rank,
+            avatar,
+            name,
+            rating,
+            wins,
+            losses
+            ORDER BY
+                id
+        )
+),
+later AS (
+    SELECT
+        id,
+        last_scrape_id,
+        rank,
+        avatar,
+        name,
+        rating,
+        wins,
+        losses,
+        ROW_NUMBER() OVER standing AS n
+    FROM
+        leaderboard
+    WHERE
+        leaderboard_scrape_id > $1
+        AND leaderboard_scrape_id <= $2
+        AND last_scrape_id >= $2 WINDOW standing AS (
+            PARTITION BY rank,
+            avatar,
+            name,
+            rating,
+            wins,
+            losses
+            ORDER BY
+                id
+        )
+),
+merged AS (
+    SELECT
+        earlier.id AS earlier_id,
+        later.id AS later_id,
+        later.last_scrape_id,
+        EXISTS (
+            SELECT
+            FROM
+                associated_leaderboard
+            WHERE
+                leaderboard_id = earlier.id
+        ) AS associated
+    FROM
+        earlier
+        INNER JOIN later USING (rank, avatar, name, rating, wins, losses, n)
+),
+extended AS (
+    UPDATE
+        leaderboard
+    SET
+        last_scrape_id = merged.last_scrape_id
+    FROM
+        merged
+    WHERE
+        leaderboard.id = merged.earlier_id
+),
+moved AS (
+    UPDATE
+        associated_leaderboard
+    SET
+        leaderboard_id = merged.earlier_id
+    FROM
+        merged
+    WHERE
+        associated_leaderboard.leaderboard_id = merged.later_id
+        AND NOT merged.associated
+),
+dropped AS (
+    DELETE FROM
+        associated_leaderboard USING merged
+    WHERE
+        associated_leaderboard.leaderboard_id = merged.later_id
+        AND merged.associated
+)
+DELETE FROM
+    leaderboard USING merged
+WHERE
+    leaderboard.id = merged.later_id;
@@ -1,24 +1,103 @@
 use super::schema::*;
-use byteorder::{LittleEndian, ReadBytesExt};
 use diesel::{
-    sql_types::{Binary, Float, Integer, Timestamp, VarChar},
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::{BigInt, Double, Integer, Timestamp, VarChar},
     Queryable,
 };
-use serde::{Serialize, Serializer};
-use std::time::SystemTime;
+use serde::Serialize;
+use std::{io::Write, time::SystemTime};
+use steam_id::SteamId;
 
 #[derive(Queryable)]
 pub struct LeaderboardScrape {
     pub id: i32,
     pub at: SystemTime,
+    pub report: Option<serde_json::Value>,
+    pub status: JobStatus,
+    pub finished_at: Option<SystemTime>,
+    pub page_count: Option<i32>,
+    pub entry_count: Option<i32>,
+    /// Responses seen during the run, counted by HTTP status.
+    pub http_statuses: Option<serde_json::Value>,
+    pub parse_errors: Option<i32>,
+    pub scraper_version: Option<String>,
+    pub source: Option<String>,
+    pub error: Option<String>,
 }
 
 #[derive(Insertable)]
 #[table_name = "leaderboard_scrape"]
-pub struct NewLeaderboardScrape {
+pub struct NewLeaderboardScrape<'a> {
     pub at: SystemTime,
+    pub status: JobStatus,
+    pub scraper_version: &'a str,
+    pub source: &'a str,
 }
 
+/// What a scrape recorded about its run, stored when it completes or fails.
+#[derive(AsChangeset)]
+#[table_name = "leaderboard_scrape"]
+pub struct ScrapeRun {
+    pub page_count: i32,
+    pub http_statuses: serde_json::Value,
+    pub parse_errors: i32,
+    pub report: serde_json::Value,
+    pub error: Option<String>,
+}
+
+/// The parse results of a scrape, rewritten when its archived pages are re-parsed.
+#[derive(AsChangeset)]
+#[table_name = "leaderboard_scrape"]
+pub struct ScrapeParse {
+    pub page_count: i32,
+    pub entry_count: i32,
+    pub parse_errors: i32,
+    pub report: serde_json::Value,
+}
+
+/// Progress of a scrape or association job. Only complete scrapes appear in the
+/// leaderboard views.
+#[derive(AsExpression, Clone, Copy, Debug, Eq, FromSqlRow, PartialEq)]
+#[sql_type = "VarChar"]
+pub enum JobStatus {
+    Running,
+    Complete,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Complete => "complete",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl ToSql<VarChar, Pg> for JobStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<VarChar, Pg>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<VarChar, Pg> for JobStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let status: String = FromSql::<VarChar, Pg>::from_sql(bytes)?;
+
+        match status.as_str() {
+            "running" => Ok(Self::Running),
+            "complete" => Ok(Self::Complete),
+            "failed" => Ok(Self::Failed),
+            status => Err(format!("unknown scrape status {status:?}").into()),
+        }
+    }
+}
+
+/// A player's standing in every complete scrape from `leaderboard_scrape_id` through
+/// `last_scrape_id`.
 #[derive(Associations, Queryable)]
 #[belongs_to(LeaderboardScrape)]
 #[table_name = "leaderboard"]
@@ -28,12 +107,13 @@ pub struct Leaderboard {
     pub rank: i32,
     pub avatar: String,
     pub name: String,
-    pub rating: f32,
+    pub rating: f64,
     pub wins: i32,
     pub losses: i32,
+    pub last_scrape_id: i32,
 }
 
-#[derive(Associations, Insertable)]
+#[derive(Associations, Clone, Copy, Insertable)]
 #[belongs_to(LeaderboardScrape)]
 #[table_name = "leaderboard"]
 pub struct NewEntry<'a> {
@@ -41,9 +121,63 @@ pub struct NewEntry<'a> {
     pub rank: i32,
     pub avatar: &'a str,
     pub name: &'a str,
-    pub rating: f32,
+    pub rating: f64,
     pub wins: i32,
     pub losses: i32,
+    /// The scrape itself; `complete_scrape` extends it while the standing is unchanged.
+    pub last_scrape_id: i32,
+}
+
+#[derive(Queryable)]
+pub struct SteamLeaderboardScrape {
+    pub id: i32,
+    pub at: SystemTime,
+    pub finished_at: Option<SystemTime>,
+    pub status: JobStatus,
+    pub scraper_version: String,
+    pub page_count: Option<i32>,
+    pub entry_count: Option<i32>,
+    pub http_statuses: Option<serde_json::Value>,
+    pub parse_errors: Option<i32>,
+    pub report: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "steam_leaderboard_scrape"]
+pub struct NewSteamLeaderboardScrape<'a> {
+    pub at: SystemTime,
+    pub status: JobStatus,
+    pub scraper_version: &'a str,
+}
+
+/// A player's standing on Line War's Steam leaderboard, which comes with their Steam ID.
+#[derive(Clone, Copy, Insertable)]
+#[table_name = "steam_leaderboard_entry"]
+pub struct NewSteamLeaderboardEntry<'a> {
+    pub steam_leaderboard_scrape_id: i32,
+    pub rank: i32,
+    pub steam_id: SteamId,
+    pub avatar: &'a str,
+    pub name: &'a str,
+    pub rating: f64,
+}
+
+#[derive(Insertable, Queryable)]
+#[table_name = "page_archive"]
+pub struct PageArchive {
+    pub hash: String,
+    pub content: Vec<u8>,
+}
+
+#[derive(Associations, Insertable, Queryable)]
+#[belongs_to(LeaderboardScrape)]
+#[belongs_to(PageArchive, foreign_key = "page_archive_hash")]
+#[table_name = "leaderboard_page"]
+pub struct LeaderboardPage {
+    pub leaderboard_scrape_id: i32,
+    pub page: i32,
+    pub page_archive_hash: String,
 }
 
 #[derive(Queryable)]
@@ -74,7 +208,7 @@ pub struct SteamAssociation {
     pub id: i32,
     pub names_id: i32,
     pub avatar_hash_id: i32,
-    pub steam_id: Vec<u8>,
+    pub steam_id: SteamId,
 }
 
 #[derive(Insertable)]
@@ -82,7 +216,48 @@ pub struct SteamAssociation {
 pub struct NewSteamAssociation {
     pub names_id: i32,
     pub avatar_hash_id: i32,
-    pub steam_id: Vec<u8>,
+    pub steam_id: SteamId,
+}
+
+/// Steam's community visibility state of a profile that anyone can view.
+pub const VISIBILITY_PUBLIC: i32 = 3;
+
+/// A player's public Steam profile as of the last `GetPlayerSummaries` sync.
+#[derive(Clone, Debug, Insertable, Queryable, Serialize)]
+#[table_name = "steam_profile"]
+pub struct SteamProfile {
+    pub steam_id: SteamId,
+    pub persona_name: String,
+    pub avatar_url: String,
+    pub profile_url: String,
+    pub country_code: Option<String>,
+    /// Steam's community visibility state; `VISIBILITY_PUBLIC` when public.
+    pub visibility: i32,
+    pub synced_at: SystemTime,
+}
+
+impl SteamProfile {
+    pub fn is_public(&self) -> bool {
+        self.visibility == VISIBILITY_PUBLIC
+    }
+}
+
+/// Minutes of Line War a player had played at a point in time.
+#[derive(Debug, Insertable, Queryable)]
+#[table_name = "playtime"]
+pub struct Playtime {
+    pub steam_id: SteamId,
+    pub at: SystemTime,
+    pub minutes: i32,
+}
+
+/// A custom profile URL and the Steam ID it resolved to.
+#[derive(Insertable, Queryable)]
+#[table_name = "steam_vanity_url"]
+pub struct VanityUrl {
+    pub vanity_url: String,
+    pub steam_id: SteamId,
+    pub resolved_at: SystemTime,
 }
 
 #[derive(Associations, Insertable, Queryable)]
@@ -94,57 +269,48 @@ pub struct AssociatedLeaderboard {
     pub leaderboard_id: i32,
 }
 
+/// Candidate Steam accounts for a player that were not confident enough to accept.
+#[derive(Associations, Insertable, Queryable)]
+#[belongs_to(AvatarHash)]
+#[belongs_to(Names)]
+#[table_name = "association_review"]
+pub struct AssociationReview {
+    pub names_id: i32,
+    pub avatar_hash_id: i32,
+    pub candidates: serde_json::Value,
+    pub at: SystemTime,
+    /// Set when several accounts fit equally well, rather than none fitting well enough.
+    pub ambiguous: bool,
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct SharedAvatarHash {
+    #[sql_type = "VarChar"]
+    pub hash: String,
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct ScrapeId {
+    #[sql_type = "Integer"]
+    pub leaderboard_scrape_id: i32,
+}
+
 #[derive(Debug, Queryable, Serialize)]
 pub struct LeaderboardEntry {
     pub rank: i32,
     pub avatar_url: String,
     pub name: String,
-    pub rating: f32,
+    pub rating: f64,
     pub wins: i32,
     pub losses: i32,
-    #[serde(serialize_with = "serialize_steam_id")]
-    pub steam_id: Option<Vec<u8>>,
-}
-
-fn serialize_steam_id<S>(
-    value: &Option<Vec<u8>>,
-    serializer: S,
-) -> std::result::Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    let steam_id = match value {
-        Some(bytes) => Some(
-            bytes
-                .as_slice()
-                .read_u64::<LittleEndian>()
-                .map_err(serde::ser::Error::custom)?,
-        ),
-        None => None,
-    };
-
-    steam_id.serialize(serializer)
-}
-
-impl LeaderboardEntry {
-    pub fn get_steam_id(&self) -> Option<u64> {
-        self.steam_id
-            .as_ref()
-            .and_then(|bytes| bytes.as_slice().read_u64::<LittleEndian>().ok())
-    }
-}
-
-#[derive(Debug, Queryable, Serialize)]
-pub struct PlayerIGN {
-    pub name: String,
-    pub avatar_url: String,
+    pub steam_id: Option<SteamId>,
 }
 
 #[derive(Debug, Queryable, Serialize)]
 pub struct PlayerStatistics {
     pub timestamp: SystemTime,
     pub rank: i32,
-    pub rating: f32,
+    pub rating: f64,
     pub wins: i32,
     pub losses: i32,
 }
@@ -155,20 +321,53 @@ pub struct RecentLeaderboard {
     pub rank: i32,
     #[sql_type = "VarChar"]
     pub name: String,
-    #[sql_type = "Float"]
-    pub rating: f32,
+    #[sql_type = "Double"]
+    pub rating: f64,
     #[sql_type = "Integer"]
     pub wins: i32,
     #[sql_type = "Integer"]
     pub losses: i32,
-    #[sql_type = "Binary"]
-    pub steam_id: Vec<u8>,
+    #[sql_type = "BigInt"]
+    pub steam_id: SteamId,
     #[sql_type = "Timestamp"]
     pub last_at: SystemTime,
 }
 
-impl RecentLeaderboard {
-    pub fn get_steam_id(&self) -> u64 {
-        self.steam_id.as_slice().read_u64::<LittleEndian>().unwrap_or(0)
-    }
+#[derive(Debug, Queryable)]
+pub struct AssociationJob {
+    pub id: i32,
+    pub started_at: SystemTime,
+    pub finished_at: Option<SystemTime>,
+    pub status: JobStatus,
+    pub scraper_version: String,
+    pub names_indexed: i32,
+    pub avatars_hashed: i32,
+    pub players_found: i32,
+    pub players_associated: i32,
+    pub players_queued: i32,
+    pub lookup_failures: i32,
+    pub entries_associated: i32,
+    pub error: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "association_job"]
+pub struct NewAssociationJob<'a> {
+    pub started_at: SystemTime,
+    pub status: JobStatus,
+    pub scraper_version: &'a str,
+}
+
+/// Counts kept by an association run, stored when it completes or fails.
+#[derive(AsChangeset, Debug, Default)]
+#[table_name = "association_job"]
+pub struct AssociationJobRun {
+    pub names_indexed: i32,
+    pub avatars_hashed: i32,
+    pub players_found: i32,
+    pub players_associated: i32,
+    pub players_queued: i32,
+    pub lookup_failures: i32,
+    pub entries_associated: i32,
+    pub error: Option<String>,
 }
@@ -0,0 +1,16 @@
+SELECT
+    leaderboard_scrape.at,
+    leaderboard.rank,
+    leaderboard.rating,
+    leaderboard.wins,
+    leaderboard.losses
+FROM
+    associated_leaderboard
+    INNER JOIN leaderboard ON associated_leaderboard.leaderboard_id = leaderboard.id
+    INNER JOIN leaderboard_scrape ON leaderboard_scrape.id BETWEEN leaderboard.leaderboard_scrape_id
+    AND leaderboard.last_scrape_id
+WHERE
+    associated_leaderboard.steam_association_id = $1
+    AND leaderboard_scrape.status = 'complete'
+ORDER BY
+    leaderboard_scrape.at DESC;
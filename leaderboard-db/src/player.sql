@@ -0,0 +1,12 @@
+SELECT
+    steam_association.id,
+    names.name,
+    avatar_hash.hash
+FROM
+    steam_association
+    INNER JOIN names ON steam_association.names_id = names.id
+    INNER JOIN avatar_hash ON steam_association.avatar_hash_id = avatar_hash.id
+WHERE
+    steam_association.steam_id = $1
+LIMIT
+    1;
@@ -0,0 +1,34 @@
+WITH appearance AS (
+    SELECT
+        leaderboard_scrape_id,
+        ROW_NUMBER() OVER player_at AS first,
+        ROW_NUMBER() OVER (
+            PARTITION BY COALESCE(steam_id::VARCHAR, name)
+            ORDER BY
+                at DESC
+        ) AS last,
+        ROW_NUMBER() OVER (
+            PARTITION BY COALESCE(steam_id::VARCHAR, name)
+            ORDER BY
+                rank,
+                at
+        ) AS peak_rank,
+        ROW_NUMBER() OVER (
+            PARTITION BY COALESCE(steam_id::VARCHAR, name)
+            ORDER BY
+                rating DESC,
+                at
+        ) AS peak_rating
+    FROM
+        leaderboard_view WINDOW player_at AS (
+            PARTITION BY COALESCE(steam_id::VARCHAR, name)
+            ORDER BY
+                at
+        )
+)
+SELECT
+    DISTINCT leaderboard_scrape_id
+FROM
+    appearance
+WHERE
+    1 IN (first, last, peak_rank, peak_rating);
@@ -0,0 +1,17 @@
+INSERT INTO
+    pruned_range
+SELECT
+    leaderboard.id,
+    MIN(kept.id),
+    MAX(kept.id)
+FROM
+    leaderboard
+    LEFT JOIN leaderboard_scrape kept ON kept.id BETWEEN leaderboard.leaderboard_scrape_id
+    AND leaderboard.last_scrape_id
+    AND kept.status = 'complete'
+    AND kept.id <> ALL($1)
+WHERE
+    leaderboard.leaderboard_scrape_id = ANY($1)
+    OR leaderboard.last_scrape_id = ANY($1)
+GROUP BY
+    leaderboard.id;
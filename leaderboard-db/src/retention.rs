@@ -0,0 +1,285 @@
+use crate::{models::JobStatus, Error, Result};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Keeps every scrape for a week, then one an hour for 90 days, then one a day.
+pub const DEFAULT_RETENTION_POLICY: &str = "all:7d,1h:90d,1d:forever";
+
+/// How densely scrapes are kept as they age, written as comma separated `INTERVAL:AGE`
+/// tiers such as `all:7d,1h:90d,1d:forever`. A scrape falls in the first tier whose age
+/// it has not reached and is kept if it is the first of its interval, or every time for
+/// `all`. Scrapes older than the last tier are pruned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetentionPolicy {
+    pub tiers: Vec<RetentionTier>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetentionTier {
+    /// Spacing of the kept scrapes; `None` keeps every scrape.
+    pub interval: Option<Duration>,
+    /// Age at which scrapes leave the tier; `None` keeps them in it forever.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        DEFAULT_RETENTION_POLICY
+            .parse()
+            .expect("invalid default retention policy")
+    }
+}
+
+impl RetentionPolicy {
+    /// Returns the ids of the scrapes to prune from finished `scrapes`, given as id, start
+    /// time and status. The newest complete scrape and every `protected` scrape are kept,
+    /// and a protected scrape stands in for the rest of its interval. Failed scrapes are
+    /// kept only while they are in the first tier, and running scrapes are never pruned.
+    pub fn select_pruned(
+        &self,
+        scrapes: &[(i32, SystemTime, JobStatus)],
+        protected: &HashSet<i32>,
+        now: SystemTime,
+    ) -> Vec<i32> {
+        let newest = scrapes
+            .iter()
+            .filter(|(_, _, status)| *status == JobStatus::Complete)
+            .max_by_key(|(id, at, _)| (*at, *id))
+            .map(|(id, _, _)| *id);
+        let mut intervals: BTreeMap<(usize, u64), Vec<(SystemTime, i32)>> = BTreeMap::new();
+        let mut pruned = Vec::new();
+
+        let kept = |id: &i32| Some(*id) == newest || protected.contains(id);
+
+        for (id, at, status) in scrapes.iter().copied() {
+            let age = now.duration_since(at).unwrap_or_default();
+            let tier = self.tiers.iter().position(|tier| match tier.max_age {
+                Some(max_age) => age < max_age,
+                None => true,
+            });
+
+            match (status, tier) {
+                (JobStatus::Running, _) | (JobStatus::Failed, Some(0)) => continue,
+                (_, None) | (JobStatus::Failed, _) if kept(&id) => continue,
+                (JobStatus::Failed, _) | (_, None) => pruned.push(id),
+                (JobStatus::Complete, Some(tier)) => {
+                    if let Some(interval) = self.tiers[tier].interval {
+                        let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+                        let bucket = since_epoch.as_secs() / interval.as_secs().max(1);
+
+                        intervals.entry((tier, bucket)).or_default().push((at, id));
+                    }
+                }
+            }
+        }
+
+        for mut scrapes in intervals.into_values() {
+            let covered = scrapes.iter().any(|(_, id)| kept(id));
+
+            scrapes.sort();
+
+            let skip = if covered { 0 } else { 1 };
+
+            pruned.extend(
+                scrapes
+                    .into_iter()
+                    .skip(skip)
+                    .map(|(_, id)| id)
+                    .filter(|id| !kept(id)),
+            );
+        }
+
+        pruned.sort_unstable();
+        pruned
+    }
+}
+
+impl FromStr for RetentionPolicy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = |reason: &str| Error::InvalidRetentionPolicy(format!("{value:?} {reason}"));
+        let mut tiers = Vec::new();
+
+        for tier in value.split(',') {
+            let (interval, max_age) = tier
+                .trim()
+                .split_once(':')
+                .ok_or_else(|| invalid("has a tier without INTERVAL:AGE"))?;
+            let interval = match interval.trim() {
+                "all" => None,
+                interval => {
+                    Some(parse_duration(interval).ok_or_else(|| invalid("has a bad interval"))?)
+                }
+            };
+            let max_age = match max_age.trim() {
+                "forever" => None,
+                max_age => Some(parse_duration(max_age).ok_or_else(|| invalid("has a bad age"))?),
+            };
+
+            tiers.push(RetentionTier { interval, max_age });
+        }
+
+        let ages: Vec<_> = tiers.iter().map(|tier| tier.max_age).collect();
+
+        if ages.iter().rev().skip(1).any(Option::is_none) {
+            return Err(invalid("keeps a tier forever before the last one"));
+        }
+
+        if ages
+            .windows(2)
+            .any(|pair| matches!(pair, [Some(a), Some(b)] if a >= b))
+        {
+            return Err(invalid("has ages that do not increase"));
+        }
+
+        Ok(Self { tiers })
+    }
+}
+
+impl fmt::Display for RetentionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tiers: Vec<_> = self
+            .tiers
+            .iter()
+            .map(|tier| {
+                let interval = tier.interval.map(format_duration);
+                let max_age = tier.max_age.map(format_duration);
+
+                format!(
+                    "{}:{}",
+                    interval.as_deref().unwrap_or("all"),
+                    max_age.as_deref().unwrap_or("forever")
+                )
+            })
+            .collect();
+
+        f.write_str(tiers.join(",").as_str())
+    }
+}
+
+/// What `prune_scrapes` removed.
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub scrapes_removed: usize,
+    /// Leaderboard rows whose every scrape was pruned.
+    pub rows_removed: usize,
+    /// Leaderboard rows whose range was narrowed to the scrapes left in it.
+    pub rows_trimmed: usize,
+    pub pages_removed: usize,
+}
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// Parses a whole number of hours, days or weeks, such as `12h`, `7d` or `2w`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let unit = match value.chars().last()? {
+        'h' => HOUR,
+        'd' => DAY,
+        'w' => WEEK,
+        _ => return None,
+    };
+    let count: u64 = value[..value.len() - 1].parse().ok()?;
+
+    (count > 0).then(|| Duration::from_secs(count * unit))
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    match seconds {
+        _ if seconds.is_multiple_of(WEEK) => format!("{}w", seconds / WEEK),
+        _ if seconds.is_multiple_of(DAY) => format!("{}d", seconds / DAY),
+        _ => format!("{}h", seconds / HOUR),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RetentionPolicy, DEFAULT_RETENTION_POLICY};
+    use crate::models::JobStatus;
+    use std::{
+        collections::HashSet,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+
+    #[test]
+    fn test_parse_policy() {
+        let policy = RetentionPolicy::default();
+
+        assert_eq!(policy.tiers.len(), 3);
+        assert_eq!(policy.tiers[0].interval, None);
+        assert_eq!(policy.tiers[1].interval, Some(Duration::from_secs(HOUR)));
+        assert_eq!(policy.tiers[2].max_age, None);
+        assert_eq!(policy.to_string(), "all:1w,1h:90d,1d:forever");
+        assert_eq!(
+            policy.to_string().parse::<RetentionPolicy>().unwrap(),
+            policy
+        );
+
+        for invalid in [
+            "",
+            "all",
+            "1h:forever,1d:90d",
+            "all:7d,1h:7d",
+            "1x:7d",
+            "0h:7d",
+        ] {
+            assert!(invalid.parse::<RetentionPolicy>().is_err(), "{invalid}");
+        }
+
+        assert!(DEFAULT_RETENTION_POLICY.parse::<RetentionPolicy>().is_ok());
+    }
+
+    #[test]
+    fn test_select_pruned() {
+        let policy: RetentionPolicy = "all:1d,1h:7d".parse().unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(100 * DAY);
+        let ago = |seconds: u64| now - Duration::from_secs(seconds);
+        let scrapes = [
+            // Kept by the first tier, even when failed.
+            (1, ago(60), JobStatus::Complete),
+            (2, ago(120), JobStatus::Failed),
+            (3, ago(HOUR), JobStatus::Complete),
+            // Three in one hour of the second tier: only the first is kept.
+            (4, ago(2 * DAY), JobStatus::Complete),
+            (5, ago(2 * DAY - 600), JobStatus::Complete),
+            (6, ago(2 * DAY - 1200), JobStatus::Complete),
+            // A protected scrape stands in for its hour.
+            (7, ago(3 * DAY), JobStatus::Complete),
+            (8, ago(3 * DAY - 600), JobStatus::Complete),
+            // Failed scrapes leave with the first tier; running ones stay.
+            (9, ago(3 * DAY), JobStatus::Failed),
+            (10, ago(3 * DAY), JobStatus::Running),
+            // Older than every tier.
+            (11, ago(8 * DAY), JobStatus::Complete),
+            (12, ago(9 * DAY), JobStatus::Complete),
+        ];
+        let protected = HashSet::from([8, 12]);
+
+        assert_eq!(
+            policy.select_pruned(&scrapes, &protected, now),
+            vec![5, 6, 7, 9, 11]
+        );
+    }
+
+    #[test]
+    fn test_keep_newest() {
+        let policy: RetentionPolicy = "1d:7d".parse().unwrap();
+        let now = SystemTime::now();
+        let scrapes = [(1, now - Duration::from_secs(30 * DAY), JobStatus::Complete)];
+
+        assert!(policy
+            .select_pruned(&scrapes, &HashSet::new(), now)
+            .is_empty());
+    }
+}
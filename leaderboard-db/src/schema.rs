@@ -5,6 +5,34 @@ table! {
     }
 }
 
+table! {
+    association_job (id) {
+        id -> Int4,
+        started_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+        status -> Varchar,
+        scraper_version -> Varchar,
+        names_indexed -> Int4,
+        avatars_hashed -> Int4,
+        players_found -> Int4,
+        players_associated -> Int4,
+        players_queued -> Int4,
+        lookup_failures -> Int4,
+        entries_associated -> Int4,
+        error -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    association_review (names_id, avatar_hash_id) {
+        names_id -> Int4,
+        avatar_hash_id -> Int4,
+        candidates -> Jsonb,
+        at -> Timestamp,
+        ambiguous -> Bool,
+    }
+}
+
 table! {
     avatar_hash (id) {
         id -> Int4,
@@ -26,10 +54,10 @@ table! {
         rank -> Int4,
         avatar -> Varchar,
         name -> Varchar,
-        rating -> Float4,
+        rating -> Float8,
         wins -> Int4,
         losses -> Int4,
-        steam_id -> Bytea,
+        steam_id -> Int8,
     }
 }
 
@@ -40,9 +68,18 @@ table! {
         rank -> Int4,
         avatar -> Varchar,
         name -> Varchar,
-        rating -> Float4,
+        rating -> Float8,
         wins -> Int4,
         losses -> Int4,
+        last_scrape_id -> Int4,
+    }
+}
+
+table! {
+    leaderboard_page (leaderboard_scrape_id, page) {
+        leaderboard_scrape_id -> Int4,
+        page -> Int4,
+        page_archive_hash -> Varchar,
     }
 }
 
@@ -50,6 +87,16 @@ table! {
     leaderboard_scrape (id) {
         id -> Int4,
         at -> Timestamp,
+        report -> Nullable<Jsonb>,
+        status -> Varchar,
+        finished_at -> Nullable<Timestamp>,
+        page_count -> Nullable<Int4>,
+        entry_count -> Nullable<Int4>,
+        http_statuses -> Nullable<Jsonb>,
+        parse_errors -> Nullable<Int4>,
+        scraper_version -> Nullable<Varchar>,
+        source -> Nullable<Varchar>,
+        error -> Nullable<Varchar>,
     }
 }
 
@@ -61,10 +108,10 @@ table! {
         rank -> Int4,
         avatar -> Varchar,
         name -> Varchar,
-        rating -> Float4,
+        rating -> Float8,
         wins -> Int4,
         losses -> Int4,
-        steam_id -> Bytea,
+        steam_id -> Int8,
     }
 }
 
@@ -75,28 +122,104 @@ table! {
     }
 }
 
+table! {
+    page_archive (hash) {
+        hash -> Varchar,
+        content -> Bytea,
+    }
+}
+
+table! {
+    playtime (steam_id, at) {
+        steam_id -> Int8,
+        at -> Timestamp,
+        minutes -> Int4,
+    }
+}
+
 table! {
     steam_association (id) {
         id -> Int4,
         names_id -> Int4,
         avatar_hash_id -> Int4,
-        steam_id -> Bytea,
+        steam_id -> Int8,
+    }
+}
+
+table! {
+    steam_leaderboard_entry (steam_leaderboard_scrape_id, steam_id) {
+        steam_leaderboard_scrape_id -> Int4,
+        rank -> Int4,
+        steam_id -> Int8,
+        avatar -> Varchar,
+        name -> Varchar,
+        rating -> Float8,
+    }
+}
+
+table! {
+    steam_leaderboard_scrape (id) {
+        id -> Int4,
+        at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+        status -> Varchar,
+        scraper_version -> Varchar,
+        page_count -> Nullable<Int4>,
+        entry_count -> Nullable<Int4>,
+        http_statuses -> Nullable<Jsonb>,
+        parse_errors -> Nullable<Int4>,
+        report -> Nullable<Jsonb>,
+        error -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    steam_profile (steam_id) {
+        steam_id -> Int8,
+        persona_name -> Varchar,
+        avatar_url -> Varchar,
+        profile_url -> Varchar,
+        country_code -> Nullable<Varchar>,
+        visibility -> Int4,
+        synced_at -> Timestamp,
+    }
+}
+
+table! {
+    steam_vanity_url (vanity_url) {
+        vanity_url -> Varchar,
+        steam_id -> Int8,
+        resolved_at -> Timestamp,
     }
 }
 
 joinable!(associated_leaderboard -> leaderboard (leaderboard_id));
 joinable!(associated_leaderboard -> steam_association (steam_association_id));
+joinable!(association_review -> avatar_hash (avatar_hash_id));
+joinable!(association_review -> names (names_id));
 joinable!(leaderboard -> leaderboard_scrape (leaderboard_scrape_id));
+joinable!(leaderboard_page -> leaderboard_scrape (leaderboard_scrape_id));
+joinable!(leaderboard_page -> page_archive (page_archive_hash));
 joinable!(leaderboard_view -> leaderboard_scrape (leaderboard_scrape_id));
 joinable!(steam_association -> names (names_id));
 joinable!(steam_association -> avatar_hash (avatar_hash_id));
+joinable!(steam_leaderboard_entry -> steam_leaderboard_scrape (steam_leaderboard_scrape_id));
 
 allow_tables_to_appear_in_same_query!(
     associated_leaderboard,
+    association_job,
+    association_review,
     avatar_hash,
     avatar_map,
     leaderboard,
+    leaderboard_page,
     leaderboard_scrape,
     names,
+    page_archive,
+    playtime,
     steam_association,
+    steam_leaderboard_entry,
+    steam_leaderboard_scrape,
+    steam_profile,
+    steam_vanity_url,
 );
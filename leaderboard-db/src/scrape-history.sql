@@ -0,0 +1,23 @@
+SELECT
+    id,
+    at,
+    finished_at,
+    EXTRACT(
+        EPOCH
+        FROM
+            finished_at - at
+    )::FLOAT8,
+    status,
+    source,
+    scraper_version,
+    page_count,
+    entry_count,
+    http_statuses,
+    parse_errors,
+    error
+FROM
+    leaderboard_scrape
+ORDER BY
+    at DESC
+LIMIT
+    $1;
@@ -1,166 +1,229 @@
 use crate::{
-    models::{LeaderboardEntry, LeaderboardScrape, PlayerIGN, PlayerStatistics, RecentLeaderboard},
-    schema::{
-        associated_leaderboard, avatar_hash, current_leaderboard, leaderboard, leaderboard_scrape,
-        names, steam_association,
-    },
+    models::{LeaderboardEntry, PlayerStatistics, RecentLeaderboard, SteamProfile},
     Error, Result,
 };
-use chrono::{serde::ts_milliseconds, DateTime, Utc};
-use diesel::{
-    r2d2::ConnectionManager, ExpressionMethods, NullableExpressionMethods, PgConnection, QueryDsl,
-    RunQueryDsl,
+use chrono::{
+    serde::{ts_milliseconds, ts_milliseconds_option},
+    DateTime, Utc,
 };
-use r2d2::{Pool, PooledConnection};
+use deadpool_postgres::{Manager, Object, Pool};
 use serde::Serialize;
-use std::sync::Arc;
-use tokio::sync::{oneshot, Semaphore, SemaphorePermit};
+use std::{str::FromStr, time::SystemTime};
+use steam_id::SteamId;
+use tokio_postgres::{NoTls, Row};
 
-type PgConnectionManager = ConnectionManager<PgConnection>;
-type PgPooledConnection = PooledConnection<PgConnectionManager>;
-type PgPool = Pool<PgConnectionManager>;
-
-pub fn make_database_pool() -> Result<PgPool> {
+/// Connects a pool of asynchronous Postgres clients to `DATABASE_URL`.
+pub fn make_database_pool() -> Result<Pool> {
     let url = std::env::var("DATABASE_URL").map_err(Error::from)?;
-    let manager: PgConnectionManager = diesel::r2d2::ConnectionManager::new(url);
+    let config = tokio_postgres::Config::from_str(url.as_str()).map_err(Error::from)?;
+    let manager = Manager::new(config, NoTls);
 
-    diesel::r2d2::Pool::new(manager).map_err(Error::from)
+    Pool::builder(manager).build().map_err(Error::from)
 }
 
 #[derive(Clone)]
 pub struct DatabaseService {
-    pool: PgPool,
-    semaphore: Arc<Semaphore>,
+    pool: Pool,
 }
 
 impl DatabaseService {
     pub fn new() -> Result<Self> {
         let pool = make_database_pool()?;
-        let semaphore = Arc::new(Semaphore::new(pool.max_size() as usize));
 
-        Ok(Self { pool, semaphore })
+        Ok(Self { pool })
     }
 
-    async fn setup_request<'a, T>(
-        &'a self,
-    ) -> Result<(DatabaseContext<'a, T>, oneshot::Receiver<T>)> {
-        let _permit = self.semaphore.acquire().await.map_err(Error::from)?;
-        let connection = self.pool.get().map_err(Error::from)?;
-        let (tx, rx) = oneshot::channel();
-
-        Ok((
-            DatabaseContext {
-                _permit,
-                connection,
-                tx,
-            },
-            rx,
-        ))
+    async fn connection(&self) -> Result<Object> {
+        self.pool.get().await.map_err(Error::from)
     }
 
     pub async fn get_leaderboard(&self) -> Result<Leaderboard> {
-        let (context, rx) = self.setup_request().await?;
-        let LeaderboardScrape { at, .. } = get_latest_scrape(&context.connection)?;
-
-        tokio::task::spawn_blocking(move || {
-            let timestamp = at.into();
-            let result = current_leaderboard::table
-                .select((
-                    current_leaderboard::rank,
-                    current_leaderboard::avatar,
-                    current_leaderboard::name,
-                    current_leaderboard::rating,
-                    current_leaderboard::wins,
-                    current_leaderboard::losses,
-                    current_leaderboard::steam_id.nullable(),
-                ))
-                .load(&context.connection)
-                .map(|entries| Leaderboard { timestamp, entries })
-                .map_err(Error::from);
-
-            context.tx.send(result).ok();
-        });
-
-        rx.await
-            .map_err(Error::from)
-            .and_then(std::convert::identity)
+        let connection = self.connection().await?;
+        let timestamp = get_latest_scrape(&connection).await?.into();
+        let entries = connection
+            .query(
+                "SELECT rank, avatar, name, rating, wins, losses, steam_id FROM current_leaderboard",
+                &[],
+            )
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(LeaderboardEntry {
+                    rank: row.try_get(0)?,
+                    avatar_url: row.try_get(1)?,
+                    name: row.try_get(2)?,
+                    rating: row.try_get(3)?,
+                    wins: row.try_get(4)?,
+                    losses: row.try_get(5)?,
+                    steam_id: row.try_get(6)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Leaderboard { timestamp, entries })
     }
 
-    pub async fn get_player(&self, steam_id: u64) -> Result<Player> {
-        let (context, rx) = self.setup_request().await?;
-        let steam_id_bytes = steam_id.to_le_bytes();
-
-        tokio::task::spawn_blocking(move || {
-            let scrape = get_latest_scrape(&context.connection)?;
-            let (steam_association_id, player) = steam_association::table
-                .inner_join(names::table)
-                .inner_join(avatar_hash::table)
-                .select((steam_association::id, (names::name, avatar_hash::hash)))
-                .filter(steam_association::steam_id.eq(steam_id_bytes.as_slice()))
-                .first::<(i32, PlayerIGN)>(&context.connection)
-                .map_err(Error::from)?;
-
-            associated_leaderboard::table
-                .inner_join(leaderboard::table.inner_join(leaderboard_scrape::table))
-                .filter(associated_leaderboard::steam_association_id.eq(steam_association_id))
-                .select((
-                    leaderboard_scrape::at,
-                    leaderboard::rank,
-                    leaderboard::rating,
-                    leaderboard::wins,
-                    leaderboard::losses,
-                ))
-                .order(leaderboard_scrape::at.desc())
-                .load::<PlayerStatistics>(&context.connection)
-                .map(|player_rating| {
-                    let timestamp = scrape.at.into();
-                    let player = PlayerId {
-                        name: player.name,
-                        avatar: player.avatar_url,
-                        steam_id,
-                    };
-                    let history = player_rating.into_iter().map(History::from).collect();
-
-                    context.tx.send(Player {
-                        timestamp,
-                        player,
-                        history,
-                    })
+    pub async fn get_player(&self, steam_id: SteamId) -> Result<Player> {
+        let connection = self.connection().await?;
+        let timestamp = get_latest_scrape(&connection).await?.into();
+        let row = connection
+            .query_one(include_str!("player.sql"), &[&steam_id])
+            .await?;
+        let steam_association_id: i32 = row.try_get(0)?;
+        let player = PlayerId {
+            name: row.try_get(1)?,
+            avatar: row.try_get(2)?,
+            steam_id,
+        };
+        let profile = connection
+            .query_opt(
+                "SELECT steam_id, persona_name, avatar_url, profile_url, country_code, visibility, \
+                 synced_at FROM steam_profile WHERE steam_id = $1",
+                &[&steam_id],
+            )
+            .await?
+            .map(|row| steam_profile(&row))
+            .transpose()?;
+        let playtime = connection
+            .query(
+                "SELECT at, minutes FROM playtime WHERE steam_id = $1 ORDER BY at DESC",
+                &[&steam_id],
+            )
+            .await?
+            .iter()
+            .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+            .collect::<Result<Vec<(SystemTime, i32)>>>()?;
+        let hours_played = playtime.first().map(|(_, minutes)| hours(*minutes));
+        let history = connection
+            .query(include_str!("player-history.sql"), &[&steam_association_id])
+            .await?
+            .iter()
+            .map(|row| {
+                let statistics = PlayerStatistics {
+                    timestamp: row.try_get(0)?,
+                    rank: row.try_get(1)?,
+                    rating: row.try_get(2)?,
+                    wins: row.try_get(3)?,
+                    losses: row.try_get(4)?,
+                };
+                let hours_played = playtime
+                    .iter()
+                    .find(|(at, _)| *at <= statistics.timestamp)
+                    .map(|(_, minutes)| hours(*minutes));
+
+                Ok(History {
+                    hours_played,
+                    ..History::from(statistics)
                 })
-                .map_err(Error::from)
-        });
+            })
+            .collect::<Result<_>>()?;
 
-        rx.await.map_err(Error::from)
+        Ok(Player {
+            timestamp,
+            player,
+            profile,
+            hours_played,
+            history,
+        })
     }
 
     pub async fn get_recent_leaderboard(&self) -> Result<Vec<RecentLeaderboard>> {
-        let (context, rx) = self.setup_request().await?;
+        let connection = self.connection().await?;
 
-        tokio::task::spawn_blocking(move || {
-            let sql = include_str!("latest-leaderboard.sql");
+        connection
+            .query(include_str!("latest-leaderboard.sql"), &[])
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(RecentLeaderboard {
+                    rank: row.try_get(0)?,
+                    name: row.try_get(1)?,
+                    rating: row.try_get(2)?,
+                    wins: row.try_get(3)?,
+                    losses: row.try_get(4)?,
+                    steam_id: row.try_get(5)?,
+                    last_at: row.try_get(6)?,
+                })
+            })
+            .collect()
+    }
 
-            diesel::sql_query(sql)
-                .load::<RecentLeaderboard>(&context.connection)
-                .map(move |entries| context.tx.send(entries).ok())
-                .map_err(Error::from)
-        });
+    /// Returns the latest `limit` scrapes and association jobs, newest first.
+    pub async fn get_job_history(&self, limit: i64) -> Result<JobHistory> {
+        let connection = self.connection().await?;
+        let scrapes = connection
+            .query(include_str!("scrape-history.sql"), &[&limit])
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(ScrapeHistory {
+                    id: row.try_get(0)?,
+                    started_at: row.try_get::<_, SystemTime>(1)?.into(),
+                    finished_at: row.try_get::<_, Option<SystemTime>>(2)?.map(Into::into),
+                    seconds: row.try_get(3)?,
+                    status: row.try_get(4)?,
+                    source: row.try_get(5)?,
+                    scraper_version: row.try_get(6)?,
+                    page_count: row.try_get(7)?,
+                    entry_count: row.try_get(8)?,
+                    http_statuses: row.try_get(9)?,
+                    parse_errors: row.try_get(10)?,
+                    error: row.try_get(11)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let association_jobs = connection
+            .query(include_str!("association-job-history.sql"), &[&limit])
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(AssociationJobHistory {
+                    id: row.try_get(0)?,
+                    started_at: row.try_get::<_, SystemTime>(1)?.into(),
+                    finished_at: row.try_get::<_, Option<SystemTime>>(2)?.map(Into::into),
+                    seconds: row.try_get(3)?,
+                    status: row.try_get(4)?,
+                    scraper_version: row.try_get(5)?,
+                    names_indexed: row.try_get(6)?,
+                    avatars_hashed: row.try_get(7)?,
+                    players_found: row.try_get(8)?,
+                    players_associated: row.try_get(9)?,
+                    players_queued: row.try_get(10)?,
+                    lookup_failures: row.try_get(11)?,
+                    entries_associated: row.try_get(12)?,
+                    error: row.try_get(13)?,
+                })
+            })
+            .collect::<Result<_>>()?;
 
-        rx.await.map_err(Error::from)
+        Ok(JobHistory {
+            scrapes,
+            association_jobs,
+        })
     }
 }
 
-struct DatabaseContext<'a, T> {
-    _permit: SemaphorePermit<'a>,
-    connection: PgPooledConnection,
-    tx: oneshot::Sender<T>,
+async fn get_latest_scrape(connection: &Object) -> Result<SystemTime> {
+    connection
+        .query_one(
+            "SELECT at FROM leaderboard_scrape WHERE status = 'complete' ORDER BY at DESC LIMIT 1",
+            &[],
+        )
+        .await?
+        .try_get(0)
+        .map_err(Error::from)
 }
 
-fn get_latest_scrape(connection: &PgPooledConnection) -> Result<LeaderboardScrape> {
-    leaderboard_scrape::table
-        .order_by(leaderboard_scrape::at.desc())
-        .offset(1)
-        .first(connection)
-        .map_err(Error::from)
+fn steam_profile(row: &Row) -> Result<SteamProfile> {
+    Ok(SteamProfile {
+        steam_id: row.try_get(0)?,
+        persona_name: row.try_get(1)?,
+        avatar_url: row.try_get(2)?,
+        profile_url: row.try_get(3)?,
+        country_code: row.try_get(4)?,
+        visibility: row.try_get(5)?,
+        synced_at: row.try_get(6)?,
+    })
 }
 
 #[derive(Debug, Serialize)]
@@ -175,6 +238,10 @@ pub struct Player {
     #[serde(with = "ts_milliseconds")]
     pub timestamp: DateTime<Utc>,
     pub player: PlayerId,
+    /// The player's Steam profile, once `sync-profiles` has fetched it.
+    pub profile: Option<SteamProfile>,
+    /// Line War hours in the latest playtime snapshot, when the player's library is public.
+    pub hours_played: Option<f64>,
     pub history: Vec<History>,
 }
 
@@ -182,7 +249,7 @@ pub struct Player {
 pub struct PlayerId {
     pub name: String,
     pub avatar: String,
-    pub steam_id: u64,
+    pub steam_id: SteamId,
 }
 
 #[derive(Debug, Serialize)]
@@ -190,9 +257,11 @@ pub struct History {
     #[serde(with = "ts_milliseconds")]
     pub timestamp: DateTime<Utc>,
     pub rank: i32,
-    pub rating: f32,
+    pub rating: f64,
     pub wins: i32,
     pub losses: i32,
+    /// Hours played as of the latest playtime snapshot taken before this entry.
+    pub hours_played: Option<f64>,
 }
 
 impl From<PlayerStatistics> for History {
@@ -212,6 +281,59 @@ impl From<PlayerStatistics> for History {
             rating,
             wins,
             losses,
+            hours_played: None,
         }
     }
 }
+
+/// Recent pipeline runs, newest first.
+#[derive(Debug, Serialize)]
+pub struct JobHistory {
+    pub scrapes: Vec<ScrapeHistory>,
+    pub association_jobs: Vec<AssociationJobHistory>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScrapeHistory {
+    pub id: i32,
+    #[serde(with = "ts_milliseconds")]
+    pub started_at: DateTime<Utc>,
+    #[serde(with = "ts_milliseconds_option")]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Length of the run, once it has finished.
+    pub seconds: Option<f64>,
+    pub status: String,
+    pub source: Option<String>,
+    pub scraper_version: Option<String>,
+    pub page_count: Option<i32>,
+    pub entry_count: Option<i32>,
+    /// Responses counted by HTTP status, with requests that got none under `error`.
+    pub http_statuses: Option<serde_json::Value>,
+    pub parse_errors: Option<i32>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssociationJobHistory {
+    pub id: i32,
+    #[serde(with = "ts_milliseconds")]
+    pub started_at: DateTime<Utc>,
+    #[serde(with = "ts_milliseconds_option")]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Length of the run, once it has finished.
+    pub seconds: Option<f64>,
+    pub status: String,
+    pub scraper_version: String,
+    pub names_indexed: i32,
+    pub avatars_hashed: i32,
+    pub players_found: i32,
+    pub players_associated: i32,
+    pub players_queued: i32,
+    pub lookup_failures: i32,
+    pub entries_associated: i32,
+    pub error: Option<String>,
+}
+
+fn hours(minutes: i32) -> f64 {
+    minutes as f64 / 60.0
+}
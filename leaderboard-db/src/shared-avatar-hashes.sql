@@ -0,0 +1,10 @@
+SELECT
+    avatar_hash.hash
+FROM
+    avatar_hash
+    INNER JOIN avatar_map ON avatar_hash.id = avatar_map.avatar_hash_id
+    INNER JOIN leaderboard ON avatar_map.url = leaderboard.avatar
+GROUP BY
+    avatar_hash.hash
+HAVING
+    COUNT(DISTINCT leaderboard.name) >= $1;
@@ -0,0 +1,459 @@
+use crate::{
+    models::{JobStatus, NewEntry, PageArchive, ScrapeRun},
+    schema, LeaderboardDatabase, Result, StorageMode,
+};
+use diesel::{
+    connection::TransactionManager, Connection, ExpressionMethods, OptionalExtension, QueryDsl,
+    RunQueryDsl,
+};
+
+/// Writes a scrape's pages and entries as they arrive, inside one transaction that
+/// `complete` commits, so readers see either the whole scrape or none of it. A writer
+/// dropped before it completes rolls everything back.
+///
+/// Entries are written as rows of their own scrape. Fitting them into the ranges of other
+/// scrapes waits until `complete`, which holds a lock on the scrapes, so a scrape that
+/// completes while this one is still being written is taken into account.
+pub struct ScrapeWriter<'a> {
+    db: &'a LeaderboardDatabase,
+    scrape_id: i32,
+    storage: StorageMode,
+    entries: usize,
+    rows: usize,
+    open: bool,
+}
+
+impl<'a> ScrapeWriter<'a> {
+    pub(crate) fn begin(
+        db: &'a LeaderboardDatabase,
+        scrape_id: i32,
+        storage: StorageMode,
+    ) -> Result<Self> {
+        db.connection
+            .transaction_manager()
+            .begin_transaction(&db.connection)?;
+
+        Ok(Self {
+            db,
+            scrape_id,
+            storage,
+            entries: 0,
+            rows: 0,
+            open: true,
+        })
+    }
+
+    /// Stores a compressed page of the scrape.
+    pub fn archive_page(&mut self, page: i32, archive: &PageArchive) -> Result<usize> {
+        self.db.archive_page(self.scrape_id, page, archive)
+    }
+
+    /// Stores a batch of the scrape's entries and returns the number of rows inserted.
+    pub fn write_entries(&mut self, records: &[NewEntry]) -> Result<usize> {
+        let n = self.db.store_entries(records)?;
+
+        self.entries += records.len();
+        self.rows += n;
+
+        Ok(n)
+    }
+
+    /// Records the run, marks the scrape complete and commits. Rows of newer complete
+    /// scrapes whose ranges cover this one are split around it first. In delta mode, rows
+    /// whose standing is unchanged are then merged into the ranges of the neighbouring
+    /// complete scrapes. Returns the number of rows left for the scrape.
+    pub fn complete(mut self, run: &ScrapeRun) -> Result<usize> {
+        use schema::leaderboard_scrape;
+
+        self.db.lock_ranges()?;
+
+        let newer: Option<i32> = leaderboard_scrape::table
+            .filter(leaderboard_scrape::status.eq(JobStatus::Complete))
+            .filter(leaderboard_scrape::id.gt(self.scrape_id))
+            .select(leaderboard_scrape::id)
+            .first(&self.db.connection)
+            .optional()?;
+
+        if newer.is_some() {
+            self.db.isolate_scrape(self.scrape_id)?;
+        }
+
+        self.db
+            .finish_scrape(self.scrape_id, JobStatus::Complete, self.entries, run)?;
+
+        if self.storage == StorageMode::Delta {
+            self.rows -= self.db.merge_ranges(self.scrape_id)?;
+        }
+
+        // Left open until the commit succeeds, so a failed commit is rolled back on drop.
+        self.db
+            .connection
+            .transaction_manager()
+            .commit_transaction(&self.db.connection)?;
+        self.open = false;
+
+        Ok(self.rows)
+    }
+}
+
+impl Drop for ScrapeWriter<'_> {
+    fn drop(&mut self) {
+        if self.open {
+            let _ = self
+                .db
+                .connection
+                .transaction_manager()
+                .rollback_transaction(&self.db.connection);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::{
+        models::{NewEntry, ScrapeRun},
+        schema, LeaderboardDatabase, StorageMode,
+    };
+    use diesel::{
+        sql_types::{BigInt, Integer, VarChar},
+        Connection, ExpressionMethods, NullableExpressionMethods, PgConnection, QueryDsl,
+        RunQueryDsl,
+    };
+
+    /// Connects to `TEST_DATABASE_URL` inside a transaction that is never committed.
+    pub(crate) fn connect() -> LeaderboardDatabase {
+        let database_url =
+            std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+        let connection = PgConnection::establish(database_url.as_str()).unwrap();
+
+        connection.begin_test_transaction().unwrap();
+
+        LeaderboardDatabase { connection }
+    }
+
+    fn start(db: &LeaderboardDatabase) -> i32 {
+        db.start_scrape("html", "test").unwrap().id
+    }
+
+    fn entries<'a>(scrape_id: i32, standings: &[(&'a str, f64)]) -> Vec<NewEntry<'a>> {
+        standings
+            .iter()
+            .zip(1..)
+            .map(|(&(name, rating), rank)| NewEntry {
+                leaderboard_scrape_id: scrape_id,
+                rank,
+                avatar: name,
+                name,
+                rating,
+                wins: 0,
+                losses: 0,
+                last_scrape_id: scrape_id,
+            })
+            .collect()
+    }
+
+    fn run() -> ScrapeRun {
+        ScrapeRun {
+            page_count: 1,
+            http_statuses: serde_json::json!({}),
+            parse_errors: 0,
+            report: serde_json::json!({}),
+            error: None,
+        }
+    }
+
+    fn complete(
+        db: &LeaderboardDatabase,
+        scrape_id: i32,
+        standings: &[(&str, f64)],
+        storage: StorageMode,
+    ) -> usize {
+        db.complete_scrape(
+            scrape_id,
+            &[],
+            entries(scrape_id, standings).as_slice(),
+            &run(),
+            storage,
+        )
+        .unwrap()
+    }
+
+    /// Returns each player's name, rating and Steam ID in a scrape, as the leaderboard views
+    /// show them.
+    fn view(db: &LeaderboardDatabase, scrape_id: i32) -> Vec<(String, f64, Option<i64>)> {
+        use schema::leaderboard_view;
+
+        leaderboard_view::table
+            .filter(leaderboard_view::leaderboard_scrape_id.eq(scrape_id))
+            .order(leaderboard_view::rank)
+            .select((
+                leaderboard_view::name,
+                leaderboard_view::rating,
+                leaderboard_view::steam_id.nullable(),
+            ))
+            .load(&db.connection)
+            .unwrap()
+    }
+
+    /// Counts the rows stored for scrapes from `first` on.
+    fn rows(db: &LeaderboardDatabase, first: i32) -> i64 {
+        use schema::leaderboard;
+
+        leaderboard::table
+            .filter(leaderboard::last_scrape_id.ge(first))
+            .count()
+            .get_result(&db.connection)
+            .unwrap()
+    }
+
+    /// Associates a player's row in a scrape with a Steam ID.
+    fn associate(db: &LeaderboardDatabase, scrape_id: i32, name: &str, steam_id: i64) {
+        diesel::sql_query(
+            "WITH names AS (INSERT INTO names (name) VALUES ($1) RETURNING id), \
+             hash AS (INSERT INTO avatar_hash (hash) VALUES ($1) RETURNING id), \
+             association AS (INSERT INTO steam_association (names_id, avatar_hash_id, steam_id) \
+             SELECT names.id, hash.id, $3 FROM names, hash RETURNING id) \
+             INSERT INTO associated_leaderboard SELECT leaderboard.id, association.id \
+             FROM leaderboard, association WHERE name = $1 \
+             AND $2 BETWEEN leaderboard_scrape_id AND last_scrape_id",
+        )
+        .bind::<VarChar, _>(name)
+        .bind::<Integer, _>(scrape_id)
+        .bind::<BigInt, _>(steam_id)
+        .execute(&db.connection)
+        .unwrap();
+    }
+
+    fn expected(standings: &[(&str, f64, Option<i64>)]) -> Vec<(String, f64, Option<i64>)> {
+        standings
+            .iter()
+            .map(|&(name, rating, steam_id)| (name.to_string(), rating, steam_id))
+            .collect()
+    }
+
+    #[test]
+    #[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+    fn test_delta_extends_unchanged_rows() {
+        let db = connect();
+        let standings = [("writer-a", 1500.0), ("writer-b", 1400.0)];
+        let first = start(&db);
+
+        assert_eq!(complete(&db, first, &standings, StorageMode::Delta), 2);
+
+        let second = start(&db);
+
+        assert_eq!(complete(&db, second, &standings, StorageMode::Delta), 0);
+        assert_eq!(rows(&db, first), 2);
+
+        for scrape_id in [first, second] {
+            assert_eq!(
+                view(&db, scrape_id),
+                expected(&[("writer-a", 1500.0, None), ("writer-b", 1400.0, None)])
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+    fn test_delta_stores_changed_rows() {
+        let db = connect();
+        let first = start(&db);
+
+        complete(
+            &db,
+            first,
+            &[("writer-a", 1500.0), ("writer-b", 1400.0)],
+            StorageMode::Delta,
+        );
+
+        let second = start(&db);
+
+        assert_eq!(
+            complete(
+                &db,
+                second,
+                &[("writer-b", 1550.0), ("writer-a", 1500.0)],
+                StorageMode::Delta,
+            ),
+            2
+        );
+        assert_eq!(rows(&db, first), 4);
+        assert_eq!(
+            view(&db, first),
+            expected(&[("writer-a", 1500.0, None), ("writer-b", 1400.0, None)])
+        );
+        assert_eq!(
+            view(&db, second),
+            expected(&[("writer-b", 1550.0, None), ("writer-a", 1500.0, None)])
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+    fn test_delta_out_of_order_keeps_associations() {
+        let db = connect();
+        let standings = [("writer-a", 1500.0), ("writer-b", 1400.0)];
+        let first = start(&db);
+        let middle = start(&db);
+        let last = start(&db);
+
+        complete(&db, first, &standings, StorageMode::Delta);
+        complete(&db, last, &standings, StorageMode::Delta);
+        associate(&db, last, "writer-a", 1);
+        associate(&db, last, "writer-b", 2);
+
+        assert_eq!(rows(&db, first), 2);
+
+        complete(
+            &db,
+            middle,
+            &[("writer-a", 1500.0), ("writer-b", 1450.0)],
+            StorageMode::Delta,
+        );
+
+        // writer-a is merged back into one range, while writer-b is split around the
+        // changed standing and its parts keep the association.
+        assert_eq!(rows(&db, first), 4);
+        assert_eq!(
+            view(&db, middle),
+            expected(&[("writer-a", 1500.0, Some(1)), ("writer-b", 1450.0, None)])
+        );
+
+        for scrape_id in [first, last] {
+            assert_eq!(
+                view(&db, scrape_id),
+                expected(&[("writer-a", 1500.0, Some(1)), ("writer-b", 1400.0, Some(2))])
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+    fn test_full_isolates_scrape() {
+        let db = connect();
+        let standings = [("writer-a", 1500.0), ("writer-b", 1400.0)];
+        let first = start(&db);
+        let middle = start(&db);
+        let last = start(&db);
+
+        complete(&db, first, &standings, StorageMode::Delta);
+        complete(&db, last, &standings, StorageMode::Delta);
+        complete(&db, middle, &standings, StorageMode::Full);
+
+        for scrape_id in [first, middle, last] {
+            assert_eq!(
+                view(&db, scrape_id),
+                expected(&[("writer-a", 1500.0, None), ("writer-b", 1400.0, None)])
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+    fn test_replace_entries_merges_ranges() {
+        let db = connect();
+        let standings = [("writer-a", 1500.0), ("writer-b", 1400.0)];
+        let scrapes = [start(&db), start(&db), start(&db)];
+
+        for scrape_id in scrapes {
+            complete(&db, scrape_id, &standings, StorageMode::Delta);
+        }
+
+        associate(&db, scrapes[0], "writer-a", 1);
+
+        assert_eq!(
+            db.replace_entries(scrapes[1], entries(scrapes[1], &standings).as_slice())
+                .unwrap(),
+            2
+        );
+        assert_eq!(rows(&db, scrapes[0]), 2);
+
+        db.replace_entries(
+            scrapes[1],
+            entries(scrapes[1], &[("writer-a", 1500.0), ("writer-b", 1450.0)]).as_slice(),
+        )
+        .unwrap();
+
+        assert_eq!(rows(&db, scrapes[0]), 4);
+        assert_eq!(
+            view(&db, scrapes[1]),
+            expected(&[("writer-a", 1500.0, Some(1)), ("writer-b", 1450.0, None)])
+        );
+
+        for scrape_id in [scrapes[0], scrapes[2]] {
+            assert_eq!(
+                view(&db, scrape_id),
+                expected(&[("writer-a", 1500.0, Some(1)), ("writer-b", 1400.0, None)])
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+    fn test_writer_completes_after_newer_scrape() {
+        let db = connect();
+        let standings = [("writer-a", 1500.0), ("writer-b", 1400.0)];
+        let first = start(&db);
+
+        complete(&db, first, &standings, StorageMode::Delta);
+
+        let middle = start(&db);
+        let last = start(&db);
+        let mut writer = db.write_scrape(middle, StorageMode::Delta).unwrap();
+
+        writer
+            .write_entries(entries(middle, &standings).as_slice())
+            .unwrap();
+
+        // The newer scrape completes while the middle one is still open, extending the
+        // first scrape's rows over both.
+        assert_eq!(complete(&db, last, &standings, StorageMode::Delta), 0);
+        assert_eq!(rows(&db, first), 4);
+        assert_eq!(writer.complete(&run()).unwrap(), 0);
+        assert_eq!(rows(&db, first), 2);
+
+        for scrape_id in [first, middle, last] {
+            assert_eq!(
+                view(&db, scrape_id),
+                expected(&[("writer-a", 1500.0, None), ("writer-b", 1400.0, None)])
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+    fn test_delta_matches_full() {
+        let scrapes = [
+            vec![("writer-a", 1500.0), ("writer-b", 1400.0)],
+            vec![
+                ("writer-a", 1500.0),
+                ("writer-b", 1400.0),
+                ("writer-c", 1300.0),
+            ],
+            vec![
+                ("writer-b", 1550.0),
+                ("writer-a", 1500.0),
+                ("writer-c", 1300.0),
+            ],
+            vec![("writer-b", 1550.0), ("writer-a", 1500.0)],
+            vec![("writer-b", 1550.0), ("writer-a", 1500.0)],
+        ];
+        // Completed out of order, as overlapping runs would.
+        let order = [0, 2, 1, 4, 3];
+        let views: Vec<_> = [StorageMode::Full, StorageMode::Delta]
+            .into_iter()
+            .map(|storage| {
+                let db = connect();
+                let ids: Vec<_> = scrapes.iter().map(|_| start(&db)).collect();
+
+                for &i in order.iter() {
+                    complete(&db, ids[i], &scrapes[i], storage);
+                }
+
+                ids.iter().map(|&id| view(&db, id)).collect::<Vec<_>>()
+            })
+            .collect();
+
+        assert_eq!(views[0], views[1]);
+    }
+}
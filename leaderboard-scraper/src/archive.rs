@@ -0,0 +1,66 @@
+use crate::Result;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+/// A fetched page compressed for storage and addressed by the SHA-256 of its HTML, so
+/// identical pages are only stored once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArchivedPage {
+    pub hash: String,
+    pub content: Vec<u8>,
+}
+
+impl ArchivedPage {
+    pub fn new(html: &str) -> Result<Self> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+
+        encoder.write_all(html.as_bytes())?;
+
+        Ok(Self {
+            hash: content_hash(html),
+            content: encoder.finish()?,
+        })
+    }
+
+    /// Decompresses the archived HTML.
+    pub fn html(&self) -> Result<String> {
+        let mut html = String::new();
+
+        GzDecoder::new(self.content.as_slice()).read_to_string(&mut html)?;
+
+        Ok(html)
+    }
+}
+
+/// Returns the lowercase hex SHA-256 digest of `html`.
+pub fn content_hash(html: &str) -> String {
+    Sha256::digest(html.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{content_hash, ArchivedPage};
+
+    #[test]
+    fn test_archive_round_trip() {
+        let html = "<table class=\"rankTable\"><tr><td>#1</td></tr></table>".repeat(100);
+        let page = ArchivedPage::new(html.as_str()).unwrap();
+
+        assert!(page.content.len() < html.len());
+        assert_eq!(page.hash, content_hash(html.as_str()));
+        assert_eq!(page.html().unwrap(), html);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(content_hash("a"), content_hash("b"));
+    }
+}
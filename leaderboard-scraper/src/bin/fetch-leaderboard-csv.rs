@@ -2,10 +2,17 @@ use leaderboard_scraper::scrape_leaderboard;
 
 #[tokio::main]
 async fn main() {
-    let leaderboard = scrape_leaderboard()
+    let (leaderboard, report) = scrape_leaderboard()
         .await
         .expect("Error fetching the Line War leaderboard.");
 
+    for skipped in report.rows_skipped.iter() {
+        eprintln!(
+            "skipped page {:?} row {}: {}",
+            skipped.page, skipped.row, skipped.reason
+        );
+    }
+
     println!("Rank, Avatar, Name, Rating, Wins, Losses");
 
     for entry in leaderboard.iter() {
@@ -0,0 +1,245 @@
+use crate::{scrape::ColumnMap, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Selectors and column mapping used to scrape Line War and Steam pages. Every field has a
+/// built-in default, so a config file only needs the values that differ.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ScrapeConfig {
+    pub endpoints: EndpointConfig,
+    pub selectors: SelectorConfig,
+    pub columns: ColumnConfig,
+    pub steam_leaderboard: SteamLeaderboardConfig,
+}
+
+impl ScrapeConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+
+        toml::from_str(text.as_str()).map_err(Into::into)
+    }
+
+    /// Loads the file named by `SCRAPER_CONFIG`, or the defaults when it is not set, then
+    /// applies any endpoint overrides from the environment.
+    pub fn from_env() -> Result<Self> {
+        let mut config = match std::env::var_os("SCRAPER_CONFIG") {
+            Some(path) => Self::load(path)?,
+            None => Self::default(),
+        };
+
+        config.endpoints.apply_env();
+
+        Ok(config)
+    }
+}
+
+/// Base URLs of the services the scraper talks to.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct EndpointConfig {
+    /// The Line War leaderboard page; `LINEWAR_LEADERBOARD_URL` overrides it.
+    pub leaderboard: String,
+    /// The Steam Community site; `STEAM_COMMUNITY_URL` overrides it.
+    pub steam_community: String,
+    /// The Steam Web API; `STEAM_API_URL` overrides it.
+    pub steam_api: String,
+}
+
+impl Default for EndpointConfig {
+    fn default() -> Self {
+        Self {
+            leaderboard: "https://linewar.com/Leaderboard/Index".into(),
+            steam_community: "https://steamcommunity.com".into(),
+            steam_api: "http://api.steampowered.com".into(),
+        }
+    }
+}
+
+impl EndpointConfig {
+    /// Builds the endpoints from the defaults and environment overrides alone.
+    pub fn from_env() -> Self {
+        let mut endpoints = Self::default();
+
+        endpoints.apply_env();
+
+        endpoints
+    }
+
+    pub fn steam_community_url(&self, path: &str) -> String {
+        join_url(self.steam_community.as_str(), path)
+    }
+
+    pub fn steam_api_url(&self, path: &str) -> String {
+        join_url(self.steam_api.as_str(), path)
+    }
+
+    fn apply_env(&mut self) {
+        let vars = [
+            ("LINEWAR_LEADERBOARD_URL", &mut self.leaderboard),
+            ("STEAM_COMMUNITY_URL", &mut self.steam_community),
+            ("STEAM_API_URL", &mut self.steam_api),
+        ];
+
+        for (name, value) in vars {
+            if let Ok(url) = std::env::var(name) {
+                *value = url;
+            }
+        }
+    }
+}
+
+fn join_url(base: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        base.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct SelectorConfig {
+    /// The leaderboard table.
+    pub rank_table: String,
+    /// Rows within the leaderboard table.
+    pub rank_row: String,
+    /// Data cells within a leaderboard row.
+    pub rank_cell: String,
+    /// Header cells within a leaderboard row.
+    pub header_cell: String,
+    /// The avatar image within the avatar cell.
+    pub avatar_image: String,
+    /// Results within a Steam user search.
+    pub user_row: String,
+    /// The persona name link within a search result.
+    pub user_name: String,
+    /// The avatar image within a search result.
+    pub user_avatar: String,
+    /// The profile link within a search result.
+    pub user_link: String,
+}
+
+impl Default for SelectorConfig {
+    fn default() -> Self {
+        Self {
+            rank_table: "table.rankTable".into(),
+            rank_row: "tr".into(),
+            rank_cell: "td".into(),
+            header_cell: "th".into(),
+            avatar_image: "img".into(),
+            user_row: "div.search_row".into(),
+            user_name: "a.searchPersonaName".into(),
+            user_avatar: "div.avatarMedium > a > img".into(),
+            user_link: "div.avatarMedium > a".into(),
+        }
+    }
+}
+
+/// Header labels accepted for each leaderboard column, compared after trimming and
+/// lowercasing. `positions` applies to tables without a header row.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ColumnConfig {
+    /// Header of the rank table when the configuration was written; differences are logged.
+    pub expected_header: Vec<String>,
+    pub rank: Vec<String>,
+    /// Labels for the avatar column, which is otherwise the nearest blank label before the name.
+    pub avatar: Vec<String>,
+    pub name: Vec<String>,
+    pub rating: Vec<String>,
+    pub wins: Vec<String>,
+    pub losses: Vec<String>,
+    pub positions: ColumnMap,
+}
+
+impl Default for ColumnConfig {
+    fn default() -> Self {
+        let labels = |labels: &[&str]| labels.iter().map(|label| label.to_string()).collect();
+
+        Self {
+            expected_header: labels(&["rank", "", "name", "rating", "wins", "losses", ""]),
+            rank: labels(&["rank", "#"]),
+            avatar: labels(&["avatar"]),
+            name: labels(&["name", "player"]),
+            rating: labels(&["rating"]),
+            wins: labels(&["wins"]),
+            losses: labels(&["losses"]),
+            positions: ColumnMap::default(),
+        }
+    }
+}
+
+/// Where Line War keeps its ranking among the game's Steam leaderboards.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct SteamLeaderboardConfig {
+    /// Line War's Steam app ID; the Steam source and ownership checks are unavailable
+    /// without it.
+    pub app_id: Option<u32>,
+    /// Name of the ranked leaderboard, compared without regard to case.
+    pub name: String,
+    /// Divisor that turns a leaderboard score, which Steam keeps as an integer, into a rating.
+    pub score_scale: f64,
+}
+
+impl Default for SteamLeaderboardConfig {
+    fn default() -> Self {
+        Self {
+            app_id: None,
+            name: "rating".into(),
+            score_scale: 100.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EndpointConfig, ScrapeConfig};
+
+    #[test]
+    fn test_partial_config() {
+        const TOML: &str = r#"
+            [selectors]
+            rank_table = "table.leaderboard"
+
+            [columns]
+            rating = ["rating", "elo"]
+
+            [columns.positions]
+            rank = 0
+            avatar = 1
+            name = 2
+            rating = 5
+            wins = 3
+            losses = 4
+        "#;
+
+        let config: ScrapeConfig = toml::from_str(TOML).unwrap();
+        let defaults = ScrapeConfig::default();
+
+        assert_eq!(config.selectors.rank_table, "table.leaderboard");
+        assert_eq!(config.selectors.rank_row, defaults.selectors.rank_row);
+        assert_eq!(config.columns.rating, vec!["rating", "elo"]);
+        assert_eq!(config.columns.name, defaults.columns.name);
+        assert_eq!(config.columns.positions.rating, 5);
+        assert_eq!(config.endpoints, defaults.endpoints);
+    }
+
+    #[test]
+    fn test_endpoint_urls() {
+        let endpoints = EndpointConfig {
+            steam_community: "http://127.0.0.1:8080/steam/".into(),
+            ..EndpointConfig::default()
+        };
+
+        assert_eq!(
+            endpoints.steam_community_url("/search/users"),
+            "http://127.0.0.1:8080/steam/search/users"
+        );
+        assert_eq!(
+            endpoints.steam_api_url("ISteamUser/ResolveVanityURL/v0001"),
+            "http://api.steampowered.com/ISteamUser/ResolveVanityURL/v0001"
+        );
+    }
+}
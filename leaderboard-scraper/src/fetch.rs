@@ -1,26 +1,33 @@
-use crate::{Error, Result};
+use crate::{
+    transport::{Request, Transport},
+    Result,
+};
 
 pub const USER_AGENT: &str = "linewar.org";
 
-pub async fn fetch_leaderboard(page: impl Into<Option<u32>>) -> Result<String> {
-    let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
-    const URL: &str = "https://linewar.com/Leaderboard/Index";
-    let response = client
-        .get(URL)
-        .query(&[("page", page.into().unwrap_or(1))])
-        .send()
-        .await
-        .map_err(Error::from)?;
+pub async fn fetch_leaderboard(
+    transport: &dyn Transport,
+    url: &str,
+    page: impl Into<Option<u32>>,
+) -> Result<String> {
+    let request = Request::get(url).query("page", page.into().unwrap_or(1));
+    let response = transport.get(request).await?.error_for_status()?;
 
-    response.text().await.map_err(Error::from)
+    Ok(response.body)
 }
 
 #[cfg(test)]
 mod test {
     use super::fetch_leaderboard;
+    use crate::{config::EndpointConfig, tests::fixture_transport};
 
     #[tokio::test]
     async fn test_fetch_index() {
-        fetch_leaderboard(None).await.unwrap();
+        let transport = fixture_transport();
+        let endpoints = EndpointConfig::default();
+
+        fetch_leaderboard(transport.as_ref(), endpoints.leaderboard.as_str(), None)
+            .await
+            .unwrap();
     }
 }
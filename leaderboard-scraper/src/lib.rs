@@ -1,79 +1,253 @@
-use std::{borrow::Cow, num::ParseIntError};
+use futures_util::{future, pin_mut, stream, Stream, StreamExt, TryStreamExt};
+use std::{
+    borrow::Cow,
+    num::ParseIntError,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
+pub mod archive;
+pub mod config;
 pub mod fetch;
+pub mod matching;
+pub mod policy;
 pub mod scrape;
+pub mod source;
 pub mod steam;
+pub mod transport;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
+    #[error("{0} Steam accounts match")]
+    AmbiguousMatch(usize),
+    #[error("config error: {0}")]
+    ConfigError(#[from] toml::de::Error),
+    #[error("fixture not found: {0}")]
+    FixtureNotFound(String),
     #[error("HTML parse error: {0}")]
     HtmlParseError(String),
+    #[error("HTTP status {0}")]
+    HttpStatusError(u16),
+    #[error("invalid selector {0}")]
+    InvalidSelector(String),
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("leaderboard layout changed: {0}")]
+    LayoutChanged(String),
+    #[error("Steam leaderboard unavailable: {0}")]
+    LeaderboardUnavailable(String),
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
     #[error("parse error: {0}")]
     ParseError(Cow<'static, str>),
+    #[error("rate limited")]
+    RateLimited(Option<Duration>),
     #[error("reqwest error: {0}")]
     RequestError(#[from] reqwest::Error),
     #[error("user search aborted")]
     SearchAborted,
+    #[error("Steam session expired")]
+    SessionExpired,
     #[error("session ID not found")]
     SessionIdNotFound,
     #[error("STEAM_API_KEY must be set")]
     SteamApiKeyNotSet,
+    #[error("too many rows skipped: {skipped} of {total}")]
+    TooManySkippedRows { skipped: usize, total: usize },
     #[error("user not found")]
     UserNotFound,
 }
 
+impl Error {
+    /// Transient failures may succeed when retried; everything else is permanent.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::HttpStatusError(status) => *status == 408 || *status >= 500,
+            Self::RateLimited(_) => true,
+            Self::RequestError(error) => {
+                let status = error.status().map(|status| status.as_u16());
+
+                error.is_timeout()
+                    || error.is_connect()
+                    || matches!(status, Some(429) | Some(500..=599))
+            }
+            _ => false,
+        }
+    }
+}
+
 impl From<ParseIntError> for Error {
     fn from(err: ParseIntError) -> Self {
         Self::ParseError(err.to_string().into())
     }
 }
 
+impl From<steam_id::Error> for Error {
+    fn from(err: steam_id::Error) -> Self {
+        Self::ParseError(err.to_string().into())
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct Entry {
     pub rank: i32,
     pub avatar: String,
     pub name: String,
-    pub rating: f32,
+    pub rating: f64,
     pub wins: i32,
     pub losses: i32,
+    /// Known when the entry comes from the Steam leaderboard API.
+    pub steam_id: Option<SteamId>,
 }
 
+use config::{EndpointConfig, ScrapeConfig};
+use policy::{PolicyTransport, RequestPolicy};
+use scrape::{HtmlScraper, ParseMode, ScrapeReport};
 pub use steam::Steam;
+pub use steam_id::SteamId;
+use transport::{HttpTransport, Transport};
 
-#[derive(Debug, PartialEq)]
-pub enum SteamId {
-    Id(u64),
+/// How a search result links to a Steam profile: by Steam ID or by custom URL.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, serde::Serialize)]
+pub enum ProfileId {
+    Id(SteamId),
     Url(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct SteamUser {
     pub name: String,
     pub avatar: String,
-    pub id: SteamId,
+    pub id: ProfileId,
 }
 
-pub async fn scrape_leaderboard() -> Result<Vec<Entry>> {
-    let mut list = Vec::new();
-    let mut index = 1;
+/// Number of leaderboard pages fetched at once by `scrape_leaderboard`.
+pub const DEFAULT_CONCURRENCY: usize = 4;
 
-    loop {
-        let page = fetch::fetch_leaderboard(index).await?;
-        let mut entries = scrape::scrape_leaderboard(page.as_str())?;
+/// Version of the scraper, recorded with every pipeline run.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-        if entries.is_empty() {
-            break;
-        }
+/// Returns an HTTP transport that follows the default `RequestPolicy`.
+pub fn default_transport() -> Result<PolicyTransport<HttpTransport>> {
+    Ok(PolicyTransport::new(
+        HttpTransport::new()?,
+        RequestPolicy::default(),
+    ))
+}
+
+pub async fn scrape_leaderboard() -> Result<(Vec<Entry>, ScrapeReport)> {
+    let transport = default_transport()?;
+    let config = ScrapeConfig::from_env()?;
+    let scraper = HtmlScraper::new(&config)?;
+
+    scrape_leaderboard_with(
+        &transport,
+        &config.endpoints,
+        &scraper,
+        DEFAULT_CONCURRENCY,
+        ParseMode::Lenient,
+    )
+    .await
+}
+
+/// One scraped page of the leaderboard, along with the HTML it was parsed from.
+#[derive(Debug)]
+pub struct LeaderboardPage {
+    pub index: u32,
+    pub html: String,
+    pub entries: Vec<Entry>,
+    pub report: ScrapeReport,
+}
+
+/// Streams the leaderboard page by page, fetching up to `concurrency` pages at a time. Pages
+/// are yielded in order, so entries stay sorted by rank. The stream ends after the first page
+/// without rows, which is yielded too, or after the first error. Once either is seen, pages
+/// after it that have not been requested yet are skipped.
+pub fn stream_leaderboard<'a>(
+    transport: &'a dyn Transport,
+    endpoints: &'a EndpointConfig,
+    scraper: &'a HtmlScraper,
+    concurrency: usize,
+) -> impl Stream<Item = Result<LeaderboardPage>> + 'a {
+    // The index of the last page, once a page without rows or an error has been seen.
+    let last = Arc::new(AtomicU32::new(u32::MAX));
+
+    stream::iter(1..)
+        .map(move |index| {
+            let last = last.clone();
+
+            async move {
+                if index > last.load(Ordering::SeqCst) {
+                    return None;
+                }
+
+                let url = endpoints.leaderboard.as_str();
+                let page = match fetch::fetch_leaderboard(transport, url, index).await {
+                    Ok(html) => {
+                        scraper
+                            .scrape_leaderboard(html.as_str())
+                            .map(|(entries, report)| LeaderboardPage {
+                                index,
+                                html,
+                                entries,
+                                report,
+                            })
+                    }
+                    Err(err) => Err(err),
+                };
+
+                if page
+                    .as_ref()
+                    .map_or(true, |page| page.report.rows_total() == 0)
+                {
+                    last.fetch_min(index, Ordering::SeqCst);
+                }
+
+                Some(page)
+            }
+        })
+        .buffered(concurrency.max(1))
+        .scan(false, |done, page: Option<Result<LeaderboardPage>>| {
+            if *done {
+                return future::ready(None);
+            }
+
+            *done = match &page {
+                Some(Ok(page)) => page.report.rows_total() == 0,
+                _ => true,
+            };
 
-        list.append(&mut entries);
-        index += 1;
+            future::ready(page)
+        })
+}
+
+/// Collects every page of `stream_leaderboard`, then applies `mode` to the combined report.
+pub async fn scrape_leaderboard_with(
+    transport: &dyn Transport,
+    endpoints: &EndpointConfig,
+    scraper: &HtmlScraper,
+    concurrency: usize,
+    mode: ParseMode,
+) -> Result<(Vec<Entry>, ScrapeReport)> {
+    let pages = stream_leaderboard(transport, endpoints, scraper, concurrency);
+    let mut list = Vec::new();
+    let mut report = ScrapeReport::default();
+
+    pin_mut!(pages);
+
+    while let Some(mut page) = pages.try_next().await? {
+        report.append_page(page.index, page.report);
+        list.append(&mut page.entries);
     }
 
-    Ok(list)
+    mode.check(&report)?;
+
+    Ok((list, report))
 }
 
 pub fn parse_avatar_url(url: &str) -> Option<&str> {
@@ -85,14 +259,245 @@ pub fn parse_avatar_url(url: &str) -> Option<&str> {
 
 #[cfg(test)]
 pub(crate) mod tests {
-    use super::{fetch, parse_avatar_url, scrape};
+    use super::{
+        config::EndpointConfig,
+        fetch, parse_avatar_url,
+        scrape::{self, HtmlScraper, ParseMode},
+        scrape_leaderboard_with, stream_leaderboard,
+        transport::{
+            CountingTransport, HttpTransport, RecordingTransport, ReplayTransport, Request,
+            Response, Transport,
+        },
+        Result,
+    };
+    use futures_util::{future::BoxFuture, TryStreamExt};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Replays the responses saved in `fixtures/`, or records fresh ones from the network
+    /// when `RECORD_FIXTURES` is set.
+    pub(crate) fn fixture_transport() -> Arc<dyn Transport> {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+
+        if std::env::var_os("RECORD_FIXTURES").is_some() {
+            dotenv::dotenv().ok();
+            let http = HttpTransport::new().expect("error initializing HTTP client");
+            Arc::new(RecordingTransport::new(http, dir))
+        } else {
+            Arc::new(ReplayTransport::new(dir))
+        }
+    }
+
+    /// Serves the fixtures recorded for `host` over local HTTP and returns the server's base URL.
+    pub(crate) async fn fixture_server(host: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let fixtures = fixture_transport();
+
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buffer = vec![0u8; 8192];
+                let n = socket.read(&mut buffer).await.unwrap_or_default();
+                let head = String::from_utf8_lossy(&buffer[..n]);
+                let target = head.split_whitespace().nth(1).unwrap_or("/");
+                let url = reqwest::Url::parse(format!("http://{host}{target}").as_str()).unwrap();
+                let request = url.query_pairs().fold(
+                    Request::get(format!("https://{host}{}", url.path())),
+                    |request, (key, value)| request.query(key.as_ref(), value),
+                );
+                let response = fixtures.get(request).await.unwrap_or(Response {
+                    status: 404,
+                    headers: Vec::new(),
+                    body: String::new(),
+                });
+                let mut reply = format!(
+                    "HTTP/1.1 {} Fixture\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    response.status,
+                    response.body.len()
+                );
+
+                for (name, value) in response.headers.iter() {
+                    if !["content-length", "transfer-encoding", "connection"]
+                        .contains(&name.to_lowercase().as_str())
+                    {
+                        reply.push_str(format!("{name}: {value}\r\n").as_str());
+                    }
+                }
+
+                reply.push_str("\r\n");
+                reply.push_str(response.body.as_str());
+                socket.write_all(reply.as_bytes()).await.ok();
+            }
+        });
+
+        format!("http://{address}")
+    }
 
     #[tokio::test]
     async fn test_fetch_and_scrape() {
-        let html = fetch::fetch_leaderboard(None).await.unwrap();
-        let entries = scrape::scrape_leaderboard(html.as_str()).unwrap();
+        let transport = fixture_transport();
+        let endpoints = EndpointConfig::default();
+        let html =
+            fetch::fetch_leaderboard(transport.as_ref(), endpoints.leaderboard.as_str(), None)
+                .await
+                .unwrap();
+        let (entries, report) = scrape::scrape_leaderboard(html.as_str()).unwrap();
 
         assert_eq!(entries.len(), 250);
+        assert_eq!(report.rows_parsed, 250);
+        assert!(report.rows_skipped.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scrape_all_pages() {
+        let transport = fixture_transport();
+        let (entries, report) = scrape_leaderboard_with(
+            transport.as_ref(),
+            &EndpointConfig::default(),
+            &HtmlScraper::default(),
+            1,
+            ParseMode::Lenient,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(entries.len(), 300);
+        assert_eq!(report.pages_seen, vec![1, 2, 3]);
+        assert_eq!(report.rows_parsed, 300);
+        assert!(entries
+            .iter()
+            .enumerate()
+            .all(|(index, entry)| entry.rank == index as i32 + 1));
+    }
+
+    #[tokio::test]
+    async fn test_stream_pages() {
+        let transport = fixture_transport();
+        let endpoints = EndpointConfig::default();
+        let scraper = HtmlScraper::default();
+        let pages: Vec<_> = stream_leaderboard(transport.as_ref(), &endpoints, &scraper, 2)
+            .try_collect()
+            .await
+            .unwrap();
+        let sizes: Vec<_> = pages
+            .iter()
+            .map(|page| (page.index, page.entries.len()))
+            .collect();
+
+        assert_eq!(sizes, vec![(1, 250), (2, 50), (3, 0)]);
+        assert_eq!(pages[1].entries[0].rank, 251);
+    }
+
+    /// Delays every request and tracks the peak number of requests in flight.
+    struct SlowTransport {
+        inner: Arc<dyn Transport>,
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl Transport for SlowTransport {
+        fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+            Box::pin(async move {
+                let n = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(n, Ordering::SeqCst);
+
+                // Answer later pages sooner to check that rank order survives reordering.
+                let delay = match request.query.first() {
+                    Some((_, page)) if page == "1" => 30,
+                    _ => 10,
+                };
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+
+                let response = self.inner.get(request).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                response
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scrape_concurrent_pages() {
+        let transport = SlowTransport {
+            inner: fixture_transport(),
+            in_flight: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        };
+        let strict = ParseMode::Strict {
+            max_skip_ratio: 0.0,
+        };
+        let (entries, _) = scrape_leaderboard_with(
+            &transport,
+            &EndpointConfig::default(),
+            &HtmlScraper::default(),
+            3,
+            strict,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(entries.len(), 300);
+        assert!(entries
+            .iter()
+            .enumerate()
+            .all(|(index, entry)| entry.rank == index as i32 + 1));
+        assert_eq!(transport.peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stream_stops_after_last_page() {
+        // Page 3 is empty and answers before page 1, so the end is known before pages 4 and
+        // up would be requested.
+        let transport = CountingTransport::new(SlowTransport {
+            inner: fixture_transport(),
+            in_flight: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        });
+        let counts = transport.counts();
+        let endpoints = EndpointConfig::default();
+        let scraper = HtmlScraper::default();
+        let pages: Vec<_> = stream_leaderboard(&transport, &endpoints, &scraper, 3)
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(pages.len(), 3);
+        assert_eq!(
+            counts.snapshot().into_iter().collect::<Vec<_>>(),
+            vec![("200".to_string(), 3)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scrape_local_endpoint() {
+        let base = fixture_server("linewar.com").await;
+        let endpoints = EndpointConfig {
+            leaderboard: format!("{base}/Leaderboard/Index"),
+            ..EndpointConfig::default()
+        };
+        let transport = HttpTransport::new().unwrap();
+        let (entries, report) = scrape_leaderboard_with(
+            &transport,
+            &endpoints,
+            &HtmlScraper::default(),
+            2,
+            ParseMode::Lenient,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(entries.len(), 300);
+        assert_eq!(report.pages_seen, vec![1, 2, 3]);
     }
 
     #[test]
@@ -0,0 +1,299 @@
+use crate::{parse_avatar_url, scrape::normalize_name, ProfileId, SteamUser};
+use serde::Serialize;
+
+/// Hashes of the avatars Steam gives accounts that never set one, which say nothing about
+/// who owns an account.
+pub const DEFAULT_AVATAR_HASHES: &[&str] = &["fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb"];
+
+/// How much each signal contributes to a candidate's confidence. The name, avatar and
+/// profile weights should sum to one so that confidence stays between zero and one.
+#[derive(Clone, Debug)]
+pub struct MatchWeights {
+    pub name: f64,
+    pub avatar: f64,
+    pub profile: f64,
+    /// Share of the remaining distance to one, or to zero, that a verified library moves
+    /// confidence when it does, or does not, contain Line War.
+    pub ownership: f64,
+}
+
+impl Default for MatchWeights {
+    fn default() -> Self {
+        Self {
+            name: 0.5,
+            avatar: 0.4,
+            profile: 0.1,
+            ownership: 0.5,
+        }
+    }
+}
+
+/// A Steam search result scored against a leaderboard player.
+#[derive(Debug, Serialize)]
+pub struct Candidate {
+    pub user: SteamUser,
+    /// Similarity of the case-folded names, from zero to one.
+    pub name_similarity: f64,
+    pub avatar_match: bool,
+    /// Whether the avatar is shared by many accounts, so matching it is not evidence.
+    pub shared_avatar: bool,
+    /// Whether the profile's custom URL resembles the player name.
+    pub profile_match: bool,
+    /// Whether the account owns Line War; unknown until checked, and for hidden libraries.
+    pub owns_game: Option<bool>,
+    pub confidence: f64,
+}
+
+impl Candidate {
+    pub fn score(
+        user: SteamUser,
+        name: &str,
+        avatar_hash: &str,
+        shared_avatar: bool,
+        weights: &MatchWeights,
+    ) -> Self {
+        let name_similarity = name_similarity(user.name.as_str(), name);
+        let avatar_match = parse_avatar_url(user.avatar.as_str()) == Some(avatar_hash);
+        let profile_match = match &user.id {
+            ProfileId::Url(vanity) => {
+                let (vanity, name) = (name_key(vanity), name_key(name));
+
+                !name.is_empty()
+                    && (vanity.contains(name.as_str()) || name.contains(vanity.as_str()))
+            }
+            ProfileId::Id(_) => false,
+        };
+        let confidence = weights.name * name_similarity
+            + if avatar_match && !shared_avatar {
+                weights.avatar
+            } else {
+                0.0
+            }
+            + if profile_match { weights.profile } else { 0.0 };
+
+        Self {
+            user,
+            name_similarity,
+            avatar_match,
+            shared_avatar,
+            profile_match,
+            owns_game: None,
+            confidence,
+        }
+    }
+
+    /// Adds the result of an ownership check to the confidence. Unknown ownership, such as
+    /// for a private library, leaves it unchanged.
+    pub fn verify_ownership(&mut self, owns_game: Option<bool>, weights: &MatchWeights) {
+        self.owns_game = owns_game;
+
+        match owns_game {
+            Some(true) => self.confidence += weights.ownership * (1.0 - self.confidence),
+            Some(false) => self.confidence -= weights.ownership * self.confidence,
+            None => (),
+        }
+    }
+}
+
+/// What the association pipeline should do with a player's ranked candidates.
+#[derive(Debug)]
+pub enum Decision<'a> {
+    Accept(&'a Candidate),
+    /// Several accounts fit equally well; the reason says why.
+    Ambiguous(String),
+    /// No candidate is confident enough to accept.
+    Review,
+    NotFound,
+}
+
+/// Accepts the best candidate only when nothing else competes with it. With a shared avatar
+/// the avatar proves nothing, so a single account with the exact name, the avatar and either
+/// a matching custom URL or Line War in its library is required instead.
+pub fn decide(candidates: &[Candidate], threshold: f64) -> Decision<'_> {
+    let best = match candidates.first() {
+        Some(best) => best,
+        None => return Decision::NotFound,
+    };
+
+    if best.shared_avatar {
+        let exact: Vec<_> = candidates
+            .iter()
+            .filter(|candidate| candidate.avatar_match && candidate.name_similarity == 1.0)
+            .collect();
+
+        return match exact.as_slice() {
+            [] => Decision::Review,
+            [only] if only.profile_match || only.owns_game == Some(true) => Decision::Accept(only),
+            [_] => {
+                Decision::Ambiguous("shared avatar without profile or ownership evidence".into())
+            }
+            _ => Decision::Ambiguous(format!(
+                "{} accounts share the name and avatar",
+                exact.len()
+            )),
+        };
+    }
+
+    match candidates
+        .iter()
+        .filter(|candidate| candidate.confidence >= threshold)
+        .count()
+    {
+        0 => Decision::Review,
+        1 => Decision::Accept(best),
+        n => Decision::Ambiguous(format!("{n} accounts above the threshold")),
+    }
+}
+
+/// Scores every user and returns them from most to least likely.
+pub fn rank_candidates(
+    users: impl IntoIterator<Item = SteamUser>,
+    name: &str,
+    avatar_hash: &str,
+    shared_avatar: bool,
+    weights: &MatchWeights,
+) -> Vec<Candidate> {
+    let mut candidates: Vec<_> = users
+        .into_iter()
+        .map(|user| Candidate::score(user, name, avatar_hash, shared_avatar, weights))
+        .collect();
+
+    sort_candidates(&mut candidates);
+
+    candidates
+}
+
+/// Orders candidates from most to least likely, as after new evidence changes confidence.
+pub fn sort_candidates(candidates: &mut [Candidate]) {
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+}
+
+/// Normalizes a name for comparison: NFC, lowercase and single spaces.
+pub fn name_key(name: &str) -> String {
+    normalize_name(name)
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    strsim::normalized_levenshtein(name_key(a).as_str(), name_key(b).as_str())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        decide, name_similarity, rank_candidates, sort_candidates, Decision, MatchWeights,
+    };
+    use crate::{ProfileId, SteamId, SteamUser};
+
+    fn user(name: &str, hash: &str, id: ProfileId) -> SteamUser {
+        SteamUser {
+            name: name.into(),
+            avatar: format!("https://avatars.akamai.steamstatic.com/{hash}_medium.jpg"),
+            id,
+        }
+    }
+
+    #[test]
+    fn test_name_similarity() {
+        assert_eq!(name_similarity("Monjardin ", "monjardin"), 1.0);
+        assert_eq!(name_similarity("Jose\u{301}", "jos\u{e9}"), 1.0);
+        assert!(name_similarity("monjardin", "monjardim") > 0.8);
+        assert!(name_similarity("monjardin", "orbnet") < 0.3);
+    }
+
+    #[test]
+    fn test_rank_candidates() {
+        let users = vec![
+            user(
+                "kiel.monjardin",
+                "f5c4",
+                ProfileId::Id(SteamId::from_account_id(429753293)),
+            ),
+            user("MonJardin", "0000", ProfileId::Url("monjardin1".into())),
+            user(
+                "monjardin",
+                "c4c2",
+                ProfileId::Id(SteamId::from_account_id(60255097)),
+            ),
+        ];
+        let candidates =
+            rank_candidates(users, "monjardin", "c4c2", false, &MatchWeights::default());
+
+        assert_eq!(
+            candidates[0].user.id,
+            ProfileId::Id(SteamId::from_account_id(60255097))
+        );
+        assert!(candidates[0].avatar_match);
+        assert!((candidates[0].confidence - 0.9).abs() < 1e-9);
+
+        // A changed avatar still ranks the exact name ahead of a similar one.
+        assert_eq!(candidates[1].user.id, ProfileId::Url("monjardin1".into()));
+        assert!(candidates[1].profile_match);
+        assert!((candidates[1].confidence - 0.6).abs() < 1e-9);
+        assert!(candidates[2].confidence < candidates[1].confidence);
+        assert!(matches!(decide(&candidates, 0.85), Decision::Accept(best) if best.avatar_match));
+        assert!(matches!(decide(&candidates, 0.95), Decision::Review));
+    }
+
+    #[test]
+    fn test_shared_avatar() {
+        let weights = MatchWeights::default();
+        let users = || {
+            vec![
+                user("Player", "fef4", ProfileId::Id(SteamId::from_account_id(1))),
+                user("player", "fef4", ProfileId::Url("player".into())),
+                user(
+                    "Player2",
+                    "fef4",
+                    ProfileId::Id(SteamId::from_account_id(2)),
+                ),
+            ]
+        };
+
+        let candidates = rank_candidates(users(), "player", "fef4", true, &weights);
+        assert!(candidates
+            .iter()
+            .all(|candidate| candidate.confidence <= 0.6));
+        assert!(matches!(decide(&candidates, 0.5), Decision::Ambiguous(_)));
+
+        let candidates = rank_candidates(users(), "player", "fef4", false, &weights);
+        assert!(matches!(decide(&candidates, 0.85), Decision::Ambiguous(_)));
+
+        let candidates = rank_candidates(users().split_off(1), "player", "fef4", true, &weights);
+        assert!(
+            matches!(decide(&candidates, 0.85), Decision::Accept(best) if best.user.id == ProfileId::Url("player".into()))
+        );
+
+        let candidates = rank_candidates(Vec::new(), "player", "fef4", true, &weights);
+        assert!(matches!(decide(&candidates, 0.85), Decision::NotFound));
+    }
+
+    #[test]
+    fn test_ownership_evidence() {
+        let weights = MatchWeights::default();
+        let users = vec![
+            user("player", "fef4", ProfileId::Id(SteamId::from_account_id(1))),
+            user("player", "0000", ProfileId::Id(SteamId::from_account_id(2))),
+        ];
+        let mut candidates = rank_candidates(users, "player", "fef4", true, &weights);
+
+        assert!(matches!(decide(&candidates, 0.85), Decision::Ambiguous(_)));
+
+        candidates[0].verify_ownership(Some(true), &weights);
+        candidates[1].verify_ownership(Some(false), &weights);
+        sort_candidates(&mut candidates);
+
+        assert!((candidates[0].confidence - 0.75).abs() < 1e-9);
+        assert!((candidates[1].confidence - 0.25).abs() < 1e-9);
+        assert!(
+            matches!(decide(&candidates, 0.85), Decision::Accept(best) if best.owns_game == Some(true))
+        );
+
+        let confidence = candidates[1].confidence;
+        candidates[1].verify_ownership(None, &weights);
+        assert_eq!(candidates[1].confidence, confidence);
+    }
+}
@@ -0,0 +1,322 @@
+use crate::{
+    transport::{Request, Response, Transport},
+    Result,
+};
+use futures_util::future::BoxFuture;
+use rand::Rng;
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+use tokio::time::Instant;
+use tracing::warn;
+
+/// Retry and rate limit settings shared by every outbound request.
+#[derive(Clone, Debug)]
+pub struct RequestPolicy {
+    /// Number of times a transient failure is retried before giving up.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled after every attempt.
+    pub base_delay: Duration,
+    /// Upper bound on the delay between attempts, including `Retry-After`.
+    pub max_delay: Duration,
+    /// Sustained requests per second allowed to each host; zero disables rate limiting.
+    pub requests_per_second: f64,
+    /// Requests allowed to each host in a burst before rate limiting applies.
+    pub burst: u32,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            requests_per_second: 4.0,
+            burst: 4,
+        }
+    }
+}
+
+impl RequestPolicy {
+    /// Exponential backoff with jitter: a random delay between half and all of
+    /// `base_delay * 2^attempt`, capped at `max_delay`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Wraps a transport with retries for transient failures and a per-host token bucket.
+pub struct PolicyTransport<T> {
+    inner: T,
+    policy: RequestPolicy,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl<T> PolicyTransport<T> {
+    pub fn new(inner: T, policy: RequestPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn acquire(&self, host: &str) {
+        let wait = self
+            .buckets
+            .lock()
+            .expect("token bucket lock poisoned")
+            .entry(host.to_string())
+            .or_insert_with(|| TokenBucket::new(&self.policy))
+            .reserve();
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+impl<T: Transport> Transport for PolicyTransport<T> {
+    fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let host = reqwest::Url::parse(request.url.as_str())
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_default();
+            let mut attempt = 0;
+
+            loop {
+                self.acquire(host.as_str()).await;
+
+                let result = self.inner.get(request.clone()).await;
+                let (transient, retry_after, reason) = match &result {
+                    Ok(response) => match response.status_error() {
+                        Some(error) => (
+                            error.is_transient(),
+                            response.retry_after(),
+                            error.to_string(),
+                        ),
+                        None => return result,
+                    },
+                    Err(error) => (error.is_transient(), None, error.to_string()),
+                };
+
+                if !transient || attempt >= self.policy.max_retries {
+                    return result;
+                }
+
+                let delay = match retry_after {
+                    Some(retry_after) => retry_after.min(self.policy.max_delay),
+                    None => self.policy.backoff(attempt),
+                };
+
+                attempt += 1;
+                warn!(
+                    "{} failed ({reason}); retry {attempt}/{} in {delay:?}",
+                    request.url, self.policy.max_retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+        })
+    }
+}
+
+struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(policy: &RequestPolicy) -> Self {
+        let capacity = policy.burst.max(1) as f64;
+
+        Self {
+            capacity,
+            rate: policy.requests_per_second,
+            tokens: capacity,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Takes a token and returns how long the caller must wait before using it. The balance
+    /// may go negative, which queues callers in the order they arrived.
+    fn reserve(&mut self) -> Duration {
+        if self.rate <= 0.0 {
+            return Duration::ZERO;
+        }
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity) - 1.0;
+        self.updated = now;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PolicyTransport, RequestPolicy};
+    use crate::{
+        transport::{Request, Response, Transport},
+        Error, Result,
+    };
+    use futures_util::future::BoxFuture;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use tokio::time::Instant;
+
+    /// Answers with each queued status in turn, then with 200.
+    struct ScriptedTransport {
+        statuses: Mutex<Vec<(u16, Option<&'static str>)>>,
+        attempts: Mutex<u32>,
+    }
+
+    impl ScriptedTransport {
+        fn new(mut statuses: Vec<(u16, Option<&'static str>)>) -> Arc<Self> {
+            statuses.reverse();
+
+            Arc::new(Self {
+                statuses: Mutex::new(statuses),
+                attempts: Mutex::new(0),
+            })
+        }
+
+        fn attempts(&self) -> u32 {
+            *self.attempts.lock().unwrap()
+        }
+    }
+
+    impl Transport for Arc<ScriptedTransport> {
+        fn get(&self, _request: Request) -> BoxFuture<'_, Result<Response>> {
+            Box::pin(async move {
+                *self.attempts.lock().unwrap() += 1;
+                let (status, retry_after) =
+                    self.statuses.lock().unwrap().pop().unwrap_or((200, None));
+                let headers = retry_after
+                    .map(|value| vec![("Retry-After".to_string(), value.to_string())])
+                    .unwrap_or_default();
+
+                Ok(Response {
+                    status,
+                    headers,
+                    body: String::new(),
+                })
+            })
+        }
+    }
+
+    fn policy() -> RequestPolicy {
+        RequestPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            requests_per_second: 0.0,
+            burst: 1,
+        }
+    }
+
+    fn request() -> Request {
+        Request::get("https://linewar.com/Leaderboard/Index")
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_transient_status() {
+        let inner = ScriptedTransport::new(vec![(503, None), (502, None)]);
+        let transport = PolicyTransport::new(inner.clone(), policy());
+        let response = transport.get(request()).await.unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(inner.attempts(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_retry_permanent_status() {
+        let inner = ScriptedTransport::new(vec![(404, None)]);
+        let transport = PolicyTransport::new(inner.clone(), policy());
+        let response = transport.get(request()).await.unwrap();
+
+        assert_eq!(response.status, 404);
+        assert_eq!(inner.attempts(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_give_up_after_max_retries() {
+        let inner = ScriptedTransport::new(vec![(500, None); 10]);
+        let transport = PolicyTransport::new(inner.clone(), policy());
+        let error = transport
+            .get(request())
+            .await
+            .and_then(Response::error_for_status)
+            .unwrap_err();
+
+        assert!(matches!(error, Error::HttpStatusError(500)));
+        assert_eq!(inner.attempts(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_after() {
+        let inner = ScriptedTransport::new(vec![(429, Some("7"))]);
+        let transport = PolicyTransport::new(inner.clone(), policy());
+        let start = Instant::now();
+        let response = transport.get(request()).await.unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(inner.attempts(), 2);
+        assert!(start.elapsed() >= Duration::from_secs(7));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit() {
+        let inner = ScriptedTransport::new(Vec::new());
+        let policy = RequestPolicy {
+            requests_per_second: 1.0,
+            burst: 2,
+            ..policy()
+        };
+        let transport = PolicyTransport::new(inner.clone(), policy);
+        let start = Instant::now();
+
+        for _ in 0..4 {
+            transport.get(request()).await.unwrap();
+        }
+
+        assert!(start.elapsed() >= Duration::from_secs(2));
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = policy();
+
+        for attempt in 0..10 {
+            let expected = (policy.base_delay * 2u32.pow(attempt)).min(policy.max_delay);
+            let delay = policy.backoff(attempt);
+
+            assert!(delay >= expected / 2 && delay <= expected);
+        }
+    }
+
+    #[test]
+    fn test_error_is_transient() {
+        assert!(Error::HttpStatusError(503).is_transient());
+        assert!(Error::RateLimited(None).is_transient());
+        assert!(!Error::HttpStatusError(404).is_transient());
+        assert!(!Error::UserNotFound.is_transient());
+
+        let malformed = reqwest::Client::new().get("not a url").build().unwrap_err();
+
+        assert!(!Error::RequestError(malformed).is_transient());
+    }
+}
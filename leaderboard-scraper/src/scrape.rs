@@ -1,152 +1,488 @@
-use crate::{Entry, Error, Result, SteamId, SteamUser};
+use crate::{
+    config::{ColumnConfig, ScrapeConfig},
+    Entry, Error, ProfileId, Result, SteamUser,
+};
 use scraper::{ElementRef, Html, Selector};
-use tracing::error;
-
-fn parse_leaderboard_row(row: ElementRef) -> Result<Entry> {
-    let columns = Selector::parse("td").expect("bad selector");
-    let img = Selector::parse("img").expect("bad selector");
-    let mut iter = row.select(&columns);
-
-    let rank = iter
-        .next()
-        .ok_or_else(|| Error::ParseError("rank column missing".into()))?
-        .inner_html()
-        .trim_start_matches('#')
-        .parse()
-        .map_err(|err| Error::ParseError(format!("{err:?}").into()))?;
-    let avatar = iter
-        .next()
-        .ok_or_else(|| Error::ParseError("avatar column missing".into()))?
-        .select(&img)
-        .next()
-        .ok_or_else(|| Error::ParseError("avatar image missing".into()))?
-        .value()
-        .attr("src")
-        .unwrap_or_default()
-        .into();
-    let name = iter
-        .next()
-        .ok_or_else(|| Error::ParseError("rank column missing".into()))?
-        .inner_html()
-        .trim()
-        .into();
-    let rating = iter
-        .next()
-        .ok_or_else(|| Error::ParseError("rating column missing".into()))?
-        .inner_html()
-        .parse()
-        .map_err(|err| Error::ParseError(format!("{err:?}").into()))?;
-    let wins = iter
-        .next()
-        .ok_or_else(|| Error::ParseError("wins column missing".into()))?
-        .inner_html()
-        .parse()
-        .map_err(|err| Error::ParseError(format!("{err:?}").into()))?;
-    let losses = iter
-        .next()
-        .ok_or_else(|| Error::ParseError("losses column missing".into()))?
-        .inner_html()
-        .parse()
-        .map_err(|err| Error::ParseError(format!("{err:?}").into()))?;
-
-    Ok(Entry {
-        rank,
-        avatar,
-        name,
-        rating,
-        wins,
-        losses,
-    })
-}
-
-pub fn scrape_leaderboard(html: &str) -> Result<Vec<Entry>> {
-    let document = Html::parse_document(html);
-    let table = Selector::parse("table.rankTable").expect("bad selector");
-    let rows = Selector::parse("tr").expect("bad selector");
-    let table = document
-        .select(&table)
-        .next()
-        .ok_or_else(|| Error::ParseError("rank table not found".into()))?;
-    let mut list = Vec::new();
-
-    for row in table.select(&rows) {
-        match parse_leaderboard_row(row) {
-            Ok(entry) => list.push(entry),
-            Err(err) => error!("leaderboard parse error: {err:?}"),
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use unicode_normalization::UnicodeNormalization;
+
+/// How a scrape treats rows that fail to parse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseMode {
+    /// Skip unparsable rows and record them in the report.
+    Lenient,
+    /// Fail the scrape when the share of skipped rows exceeds `max_skip_ratio`.
+    Strict { max_skip_ratio: f64 },
+}
+
+impl ParseMode {
+    pub fn check(&self, report: &ScrapeReport) -> Result<()> {
+        match self {
+            Self::Strict { max_skip_ratio } if report.skip_ratio() > *max_skip_ratio => {
+                Err(Error::TooManySkippedRows {
+                    skipped: report.rows_skipped.len(),
+                    total: report.rows_total(),
+                })
+            }
+            _ => Ok(()),
         }
     }
+}
 
-    Ok(list)
+/// Diagnostics collected while scraping one or more pages.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ScrapeReport {
+    pub pages_seen: Vec<u32>,
+    pub rows_parsed: usize,
+    pub rows_skipped: Vec<SkippedRow>,
 }
 
-impl TryFrom<&str> for SteamId {
-    type Error = Error;
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SkippedRow {
+    /// Page number, when the row came from a paginated scrape.
+    pub page: Option<u32>,
+    /// One-based position of the row on its page.
+    pub row: usize,
+    pub reason: String,
+}
 
-    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
-        let error = || Error::ParseError("invalid Steam user URL".into());
-        let (prefix, id) = value.rsplit_once('/').ok_or_else(error)?;
-        let (_, prefix) = prefix.rsplit_once('/').ok_or_else(error)?;
+impl ScrapeReport {
+    pub fn rows_total(&self) -> usize {
+        self.rows_parsed + self.rows_skipped.len()
+    }
 
-        match prefix {
-            "id" => return Ok(SteamId::Url(id.to_string())),
-            "profiles" => return Ok(SteamId::Id(id.parse().map_err(Error::from)?)),
-            _ => return Err(error()),
+    pub fn skip_ratio(&self) -> f64 {
+        match self.rows_total() {
+            0 => 0.0,
+            total => self.rows_skipped.len() as f64 / total as f64,
+        }
+    }
+
+    /// Merges the report for a single page into this one.
+    pub fn append_page(&mut self, page: u32, other: ScrapeReport) {
+        self.pages_seen.push(page);
+        self.rows_parsed += other.rows_parsed;
+        self.rows_skipped
+            .extend(other.rows_skipped.into_iter().map(|skipped| SkippedRow {
+                page: Some(page),
+                ..skipped
+            }));
+    }
+
+    fn skip(&mut self, row: usize, error: Error) {
+        self.rows_skipped.push(SkippedRow {
+            page: None,
+            row,
+            reason: error.to_string(),
+        });
+    }
+}
+
+/// Positions of the leaderboard fields within a rank table row.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ColumnMap {
+    pub rank: usize,
+    pub avatar: usize,
+    pub name: usize,
+    pub rating: usize,
+    pub wins: usize,
+    pub losses: usize,
+}
+
+impl Default for ColumnMap {
+    fn default() -> Self {
+        Self {
+            rank: 0,
+            avatar: 1,
+            name: 2,
+            rating: 3,
+            wins: 4,
+            losses: 5,
+        }
+    }
+}
+
+/// Everything the configured selectors extract from a leaderboard page.
+#[derive(Debug, Serialize)]
+pub struct LeaderboardInspection {
+    /// Normalized header labels, when the table has a header row.
+    pub header: Option<Vec<String>>,
+    pub header_matches: bool,
+    pub columns: ColumnMap,
+    pub entries: Vec<Entry>,
+    pub report: ScrapeReport,
+}
+
+/// Everything the configured selectors extract from a Steam user search page.
+#[derive(Debug, Serialize)]
+pub struct SteamUsersInspection {
+    pub users: Vec<SteamUser>,
+    pub report: ScrapeReport,
+}
+
+/// Scrapes pages with the selectors and column mapping of a `ScrapeConfig`.
+#[derive(Clone, Debug)]
+pub struct HtmlScraper {
+    rank_table: Selector,
+    rank_row: Selector,
+    rank_cell: Selector,
+    header_cell: Selector,
+    avatar_image: Selector,
+    user_row: Selector,
+    user_name: Selector,
+    user_avatar: Selector,
+    user_link: Selector,
+    columns: ColumnConfig,
+}
+
+impl Default for HtmlScraper {
+    fn default() -> Self {
+        Self::new(&ScrapeConfig::default()).expect("bad default selector")
+    }
+}
+
+impl HtmlScraper {
+    pub fn new(config: &ScrapeConfig) -> Result<Self> {
+        let parse = |selector: &str| {
+            Selector::parse(selector)
+                .map_err(|err| Error::InvalidSelector(format!("'{selector}': {err:?}")))
+        };
+        let selectors = &config.selectors;
+
+        Ok(Self {
+            rank_table: parse(selectors.rank_table.as_str())?,
+            rank_row: parse(selectors.rank_row.as_str())?,
+            rank_cell: parse(selectors.rank_cell.as_str())?,
+            header_cell: parse(selectors.header_cell.as_str())?,
+            avatar_image: parse(selectors.avatar_image.as_str())?,
+            user_row: parse(selectors.user_row.as_str())?,
+            user_name: parse(selectors.user_name.as_str())?,
+            user_avatar: parse(selectors.user_avatar.as_str())?,
+            user_link: parse(selectors.user_link.as_str())?,
+            columns: config.columns.clone(),
+        })
+    }
+
+    /// Builds a scraper from the config file named by `SCRAPER_CONFIG`, if any.
+    pub fn from_env() -> Result<Self> {
+        Self::new(&ScrapeConfig::from_env()?)
+    }
+
+    pub fn scrape_leaderboard(&self, html: &str) -> Result<(Vec<Entry>, ScrapeReport)> {
+        let LeaderboardInspection {
+            entries, report, ..
+        } = self.inspect_leaderboard(html)?;
+
+        Ok((entries, report))
+    }
+
+    pub fn inspect_leaderboard(&self, html: &str) -> Result<LeaderboardInspection> {
+        let document = Html::parse_document(html);
+        let table = document
+            .select(&self.rank_table)
+            .next()
+            .ok_or_else(|| Error::ParseError("rank table not found".into()))?;
+        let mut entries = Vec::new();
+        let mut report = ScrapeReport::default();
+
+        // Header rows have no data cells and are not counted as skipped.
+        let mut rows = table
+            .select(&self.rank_row)
+            .filter(|row| row.select(&self.rank_cell).next().is_some())
+            .peekable();
+        let header = self.header_labels(table);
+        let header_matches = header.as_ref() == Some(&self.columns.expected_header);
+        let columns = match header.as_ref() {
+            Some(labels) => {
+                if !header_matches {
+                    warn!("rank table header changed: {labels:?}");
+                }
+
+                self.map_columns(labels.as_slice())?
+            }
+            None => {
+                let columns = self.columns.positions;
+
+                if let Some(row) = rows.peek() {
+                    self.check_structure(*row, &columns)?;
+                }
+
+                columns
+            }
+        };
+
+        for (index, row) in rows.enumerate() {
+            match self.parse_leaderboard_row(row, &columns) {
+                Ok(entry) => {
+                    report.rows_parsed += 1;
+                    entries.push(entry);
+                }
+                Err(err) => {
+                    warn!("leaderboard parse error: {err:?}");
+                    report.skip(index + 1, err);
+                }
+            }
+        }
+
+        Ok(LeaderboardInspection {
+            header,
+            header_matches,
+            columns,
+            entries,
+            report,
+        })
+    }
+
+    pub fn scrape_steam_users<T, F>(
+        &self,
+        html: &str,
+        mut filter_map: F,
+    ) -> Result<(Vec<T>, ScrapeReport)>
+    where
+        F: FnMut(SteamUser) -> Option<T>,
+    {
+        let document = Html::parse_document(html);
+        let mut list = Vec::new();
+        let mut report = ScrapeReport::default();
+
+        for (index, row) in document.select(&self.user_row).enumerate() {
+            match self.parse_steam_user_row(row) {
+                Ok(user) => {
+                    report.rows_parsed += 1;
+                    list.extend(filter_map(user));
+                }
+                Err(err) => {
+                    warn!("Steam search users parse error: {err:?}");
+                    report.skip(index + 1, err);
+                }
+            }
+        }
+
+        Ok((list, report))
+    }
+
+    pub fn inspect_steam_users(&self, html: &str) -> Result<SteamUsersInspection> {
+        let (users, report) = self.scrape_steam_users(html, Some)?;
+
+        Ok(SteamUsersInspection { users, report })
+    }
+
+    /// Returns the normalized labels of the first row made of header cells.
+    fn header_labels(&self, table: ElementRef) -> Option<Vec<String>> {
+        let header = table
+            .select(&self.rank_row)
+            .find(|row| row.select(&self.header_cell).next().is_some())?;
+
+        Some(
+            header
+                .select(&self.header_cell)
+                .map(|cell| cell.text().collect::<String>().trim().to_lowercase())
+                .collect(),
+        )
+    }
+
+    /// Maps columns by their header labels.
+    fn map_columns(&self, labels: &[String]) -> Result<ColumnMap> {
+        let find = |names: &[String], column: &str| {
+            let mut matches = labels
+                .iter()
+                .enumerate()
+                .filter(|(_, label)| names.contains(label))
+                .map(|(index, _)| index);
+
+            match (matches.next(), matches.next()) {
+                (Some(index), None) => Ok(index),
+                (None, _) => Err(Error::LayoutChanged(format!(
+                    "{column} column not found in header {labels:?}"
+                ))),
+                (Some(_), Some(_)) => Err(Error::LayoutChanged(format!(
+                    "{column} column appears more than once in header {labels:?}"
+                ))),
+            }
         };
+        let columns = &self.columns;
+        let rank = find(&columns.rank, "rank")?;
+        let name = find(&columns.name, "name")?;
+        let rating = find(&columns.rating, "rating")?;
+        let wins = find(&columns.wins, "wins")?;
+        let losses = find(&columns.losses, "losses")?;
+        // The avatar column is unlabelled; use the nearest blank label before the name.
+        let avatar = find(&columns.avatar, "avatar").or_else(|err| {
+            labels[..name]
+                .iter()
+                .rposition(|label| label.is_empty())
+                .ok_or(err)
+        })?;
+
+        Ok(ColumnMap {
+            rank,
+            avatar,
+            name,
+            rating,
+            wins,
+            losses,
+        })
+    }
+
+    /// Checks that a headerless table still has the expected positional layout.
+    fn check_structure(&self, row: ElementRef, columns: &ColumnMap) -> Result<()> {
+        let cells: Vec<_> = row.select(&self.rank_cell).collect();
+        let drift = |reason: &str| Error::LayoutChanged(format!("headerless rank table {reason}"));
+
+        if !cells
+            .get(columns.rank)
+            .map(|cell| cell.inner_html().trim().starts_with('#'))
+            .unwrap_or_default()
+        {
+            return Err(drift("has no '#' rank in the rank column"));
+        }
+
+        if cells
+            .get(columns.avatar)
+            .and_then(|cell| cell.select(&self.avatar_image).next())
+            .is_none()
+        {
+            return Err(drift("has no image in the avatar column"));
+        }
+
+        if cells
+            .get(columns.rating)
+            .and_then(|cell| cell.value().attr("title"))
+            .is_none()
+        {
+            return Err(drift("has no title in the rating column"));
+        }
+
+        Ok(())
+    }
+
+    fn parse_leaderboard_row(&self, row: ElementRef, columns: &ColumnMap) -> Result<Entry> {
+        let cells: Vec<_> = row.select(&self.rank_cell).collect();
+        let cell = |index: usize, column: &'static str| {
+            cells
+                .get(index)
+                .ok_or_else(|| Error::ParseError(format!("{column} column missing").into()))
+        };
+
+        let rank = cell(columns.rank, "rank")?
+            .inner_html()
+            .trim()
+            .trim_start_matches('#')
+            .parse()
+            .map_err(|err| Error::ParseError(format!("{err:?}").into()))?;
+        let avatar = cell(columns.avatar, "avatar")?
+            .select(&self.avatar_image)
+            .next()
+            .ok_or_else(|| Error::ParseError("avatar image missing".into()))?
+            .value()
+            .attr("src")
+            .unwrap_or_default()
+            .into();
+        let name = normalize_name(
+            cell(columns.name, "name")?
+                .text()
+                .collect::<String>()
+                .as_str(),
+        );
+        let rating = cell(columns.rating, "rating")?;
+        // The cell text is rounded to two places; the title holds the exact rating.
+        let rating = rating
+            .value()
+            .attr("title")
+            .map(str::to_string)
+            .unwrap_or_else(|| rating.inner_html())
+            .trim()
+            .parse()
+            .map_err(|err| Error::ParseError(format!("{err:?}").into()))?;
+        let wins = cell(columns.wins, "wins")?
+            .inner_html()
+            .trim()
+            .parse()
+            .map_err(|err| Error::ParseError(format!("{err:?}").into()))?;
+        let losses = cell(columns.losses, "losses")?
+            .inner_html()
+            .trim()
+            .parse()
+            .map_err(|err| Error::ParseError(format!("{err:?}").into()))?;
+
+        Ok(Entry {
+            rank,
+            avatar,
+            name,
+            rating,
+            wins,
+            losses,
+            steam_id: None,
+        })
+    }
+
+    fn parse_steam_user_row(&self, row: ElementRef) -> Result<SteamUser> {
+        let name = row
+            .select(&self.user_name)
+            .next()
+            .ok_or_else(|| Error::ParseError("user name not found".into()))?
+            .text()
+            .collect::<String>();
+        let name = normalize_name(name.as_str());
+        let avatar = row
+            .select(&self.user_avatar)
+            .next()
+            .ok_or_else(|| Error::ParseError("user avatar not found".into()))?
+            .value()
+            .attr("src")
+            .ok_or_else(|| Error::ParseError("user avatar not found".into()))?
+            .to_string();
+        let id = row
+            .select(&self.user_link)
+            .next()
+            .ok_or_else(|| Error::ParseError("user avatar not found".into()))?
+            .value()
+            .attr("href")
+            .ok_or_else(|| Error::ParseError("user avatar not found".into()))?
+            .try_into()?;
+
+        Ok(SteamUser { name, avatar, id })
     }
 }
 
-fn parse_steam_user_row(row: ElementRef) -> Result<SteamUser> {
-    let name_selector = Selector::parse("a.searchPersonaName").expect("bad selector");
-    let avatar_selector = Selector::parse("div.avatarMedium > a > img").expect("bad selector");
-    let id_selector = Selector::parse("div.avatarMedium > a").expect("bad selector");
-    let name = row
-        .select(&name_selector)
-        .next()
-        .ok_or_else(|| Error::ParseError("user name not found".into()))?
-        .inner_html();
-    let avatar = row
-        .select(&avatar_selector)
-        .next()
-        .ok_or_else(|| Error::ParseError("user avatar not found".into()))?
-        .value()
-        .attr("src")
-        .ok_or_else(|| Error::ParseError("user avatar not found".into()))?
-        .to_string();
-    let id = row
-        .select(&id_selector)
-        .next()
-        .ok_or_else(|| Error::ParseError("user avatar not found".into()))?
-        .value()
-        .attr("href")
-        .ok_or_else(|| Error::ParseError("user avatar not found".into()))?
-        .try_into()?;
-
-    Ok(SteamUser { name, avatar, id })
-}
-
-pub fn scrape_steam_users<T, F>(html: &str, mut filter_map: F) -> Result<Vec<T>>
+/// Trims a player name and converts it to Unicode Normalization Form C, so names compare
+/// equal however the page composed their characters.
+pub fn normalize_name(name: &str) -> String {
+    name.trim().nfc().collect()
+}
+
+pub fn scrape_leaderboard(html: &str) -> Result<(Vec<Entry>, ScrapeReport)> {
+    HtmlScraper::default().scrape_leaderboard(html)
+}
+
+pub fn scrape_steam_users<T, F>(html: &str, filter_map: F) -> Result<(Vec<T>, ScrapeReport)>
 where
     F: FnMut(SteamUser) -> Option<T>,
 {
-    let document = Html::parse_document(html);
-    let rows = Selector::parse("div.search_row").expect("bad selector");
-
-    Ok(document
-        .select(&rows)
-        .filter_map(|row| match parse_steam_user_row(row) {
-            Ok(user) => filter_map(user),
-            Err(err) => {
-                error!("Steam search users parse error: {err:?}");
-                None
-            }
-        })
-        .collect())
+    HtmlScraper::default().scrape_steam_users(html, filter_map)
+}
+
+impl TryFrom<&str> for ProfileId {
+    type Error = Error;
+
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        let error = || Error::ParseError("invalid Steam user URL".into());
+        let (prefix, id) = value.rsplit_once('/').ok_or_else(error)?;
+        let (_, prefix) = prefix.rsplit_once('/').ok_or_else(error)?;
+
+        match prefix {
+            "id" => Ok(ProfileId::Url(id.to_string())),
+            "profiles" => Ok(ProfileId::Id(id.parse().map_err(Error::from)?)),
+            _ => Err(error()),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::scrape_leaderboard;
-    use crate::{scrape::scrape_steam_users, SteamId};
+    use super::{normalize_name, scrape_leaderboard, HtmlScraper, ParseMode};
+    use crate::{config::ScrapeConfig, scrape::scrape_steam_users, Error, ProfileId};
 
     #[test]
     fn test_scrape_index() {
@@ -169,8 +505,10 @@ mod test {
         </html>
         "#;
 
-        let entries = scrape_leaderboard(HTML).unwrap();
+        let (entries, report) = scrape_leaderboard(HTML).unwrap();
         assert_eq!(entries.len(), 1);
+        assert_eq!(report.rows_parsed, 1);
+        assert!(report.rows_skipped.is_empty());
 
         let entry = &entries[0];
         assert_eq!(entry.rank, 1);
@@ -179,11 +517,147 @@ mod test {
             "https://steamcdn-a.akamaihd.net/steamcommunity/public/images/avatars/90/90588b809dab3942f510d8d438779fbcb20a7b29_medium.jpg"
         );
         assert_eq!(entry.name.as_str(), "Orbnet");
-        assert_eq!(entry.rating, 43.96);
+        assert_eq!(entry.rating, 43.9591045045235);
         assert_eq!(entry.wins, 73);
         assert_eq!(entry.losses, 9);
     }
 
+    #[test]
+    fn test_scrape_decoded_names() {
+        const HTML: &str = r#"
+        <table class="rankTable">
+            <tr><td>#1</td><td><img src="a.jpg" /></td><td> Tom &amp; &lt;Jerry&gt; &quot;Q&quot; </td><td title="43.96">43.96</td><td>73</td><td>9</td></tr>
+            <tr><td>#2</td><td><img src="b.jpg" /></td><td>Jose&#x301;</td><td title="12.5">12.50</td><td>1</td><td>2</td></tr>
+        </table>
+        "#;
+
+        let (entries, _) = scrape_leaderboard(HTML).unwrap();
+        assert_eq!(entries[0].name, "Tom & <Jerry> \"Q\"");
+        assert_eq!(entries[1].name, "Jos\u{e9}");
+        assert_eq!(normalize_name(" Jose\u{301} "), "Jos\u{e9}");
+    }
+
+    #[test]
+    fn test_scrape_report_skipped_rows() {
+        const HTML: &str = r#"
+        <table class="rankTable">
+            <tr><th>Rank</th><th></th><th>Name</th><th>Rating</th><th>Wins</th><th>Losses</th></tr>
+            <tr><td>#1</td><td><img src="a.jpg" /></td><td>Orbnet</td><td>43.96</td><td>73</td><td>9</td></tr>
+            <tr><td>#2</td><td><img src="b.jpg" /></td><td>Broken</td><td>n/a</td><td>1</td><td>2</td></tr>
+            <tr><td>#3</td><td><img src="c.jpg" /></td><td>Short</td></tr>
+        </table>
+        "#;
+
+        let (entries, report) = scrape_leaderboard(HTML).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(report.rows_parsed, 1);
+        assert_eq!(report.rows_skipped.len(), 2);
+        assert_eq!(report.rows_skipped[0].row, 2);
+        assert_eq!(report.rows_skipped[1].row, 3);
+        assert_eq!(
+            report.rows_skipped[1].reason,
+            "parse error: rating column missing"
+        );
+
+        assert!(ParseMode::Lenient.check(&report).is_ok());
+        assert!(ParseMode::Strict {
+            max_skip_ratio: 0.7
+        }
+        .check(&report)
+        .is_ok());
+        assert!(matches!(
+            ParseMode::Strict {
+                max_skip_ratio: 0.5
+            }
+            .check(&report),
+            Err(Error::TooManySkippedRows {
+                skipped: 2,
+                total: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_scrape_reordered_columns() {
+        const HTML: &str = r#"
+        <table class="rankTable">
+            <thead>
+                <tr><th>Rank</th><th></th><th>Player</th><th>Wins</th><th>Losses</th><th>Country</th><th>Rating</th></tr>
+            </thead>
+            <tr><td>#1</td><td><img src="a.jpg" /></td><td>Orbnet</td><td>73</td><td>9</td><td>SE</td><td title="43.9591045045235">43.96</td></tr>
+        </table>
+        "#;
+
+        let (entries, report) = scrape_leaderboard(HTML).unwrap();
+        assert_eq!(report.rows_parsed, 1);
+
+        let entry = &entries[0];
+        assert_eq!(entry.name, "Orbnet");
+        assert_eq!(entry.avatar, "a.jpg");
+        assert_eq!(entry.rating, 43.9591045045235);
+        assert_eq!(entry.wins, 73);
+        assert_eq!(entry.losses, 9);
+    }
+
+    #[test]
+    fn test_scrape_layout_changed() {
+        const MISSING: &str = r#"
+        <table class="rankTable">
+            <tr><th>Rank</th><th></th><th>Name</th><th>Score</th><th>Wins</th><th>Losses</th></tr>
+            <tr><td>#1</td><td><img src="a.jpg" /></td><td>Orbnet</td><td>43.96</td><td>73</td><td>9</td></tr>
+        </table>
+        "#;
+        const DUPLICATE: &str = r#"
+        <table class="rankTable">
+            <tr><th>Rank</th><th></th><th>Name</th><th>Rating</th><th>Wins</th><th>Wins</th><th>Losses</th></tr>
+        </table>
+        "#;
+        const HEADERLESS: &str = r#"
+        <table class="rankTable">
+            <tr><td>#1</td><td><img src="a.jpg" /></td><td>Orbnet</td><td>73</td><td title="43.9591045045235">43.96</td><td>9</td></tr>
+        </table>
+        "#;
+
+        for html in [MISSING, DUPLICATE, HEADERLESS] {
+            assert!(matches!(
+                scrape_leaderboard(html),
+                Err(Error::LayoutChanged(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_configured_selectors() {
+        const HTML: &str = r#"
+        <div class="board">
+            <div class="row head"><span>Pos</span><span></span><span>Name</span><span>Elo</span><span>Wins</span><span>Losses</span></div>
+            <div class="row"><span>#1</span><span><img src="a.jpg" /></span><span>Orbnet</span><span>43.96</span><span>73</span><span>9</span></div>
+        </div>
+        "#;
+
+        let mut config = ScrapeConfig::default();
+        config.selectors.rank_table = "div.board".into();
+        config.selectors.rank_row = "div.row".into();
+        config.selectors.rank_cell = "div.row:not(.head) > span".into();
+        config.selectors.header_cell = "div.head > span".into();
+        config.columns.rank.push("pos".into());
+        config.columns.rating.push("elo".into());
+
+        let scraper = HtmlScraper::new(&config).unwrap();
+        let inspection = scraper.inspect_leaderboard(HTML).unwrap();
+        assert!(!inspection.header_matches);
+        assert_eq!(inspection.columns.rating, 3);
+        assert_eq!(inspection.entries.len(), 1);
+        assert_eq!(inspection.entries[0].name, "Orbnet");
+        assert_eq!(inspection.entries[0].rating, 43.96);
+
+        config.selectors.user_row = "div[".into();
+        assert!(matches!(
+            HtmlScraper::new(&config),
+            Err(Error::InvalidSelector(_))
+        ));
+    }
+
     #[test]
     fn test_scrape_steam_users() {
         const HTML: &str = r##"
@@ -225,17 +699,36 @@ mod test {
 </div>
         "##;
 
-        let users = scrape_steam_users(HTML, Some).unwrap();
+        let (users, report) = scrape_steam_users(HTML, Some).unwrap();
         assert_eq!(users.len(), 2);
+        assert_eq!(report.rows_parsed, 2);
 
         let user = &users[0];
         assert_eq!(user.name, "monjardin");
         assert_eq!(user.avatar, "https://avatars.akamai.steamstatic.com/c4c2152dfa696da706cd5484dc0d4de10fa062a0_medium.jpg");
-        assert_eq!(user.id, SteamId::Url("monjardin1".into()));
+        assert_eq!(user.id, ProfileId::Url("monjardin1".into()));
 
         let user = &users[1];
         assert_eq!(user.name, "kiel.monjardin");
         assert_eq!(user.avatar, "https://avatars.akamai.steamstatic.com/f5c43cf3801a81c5f9f7e2a791a5f6b0b705bcc1_medium.jpg");
-        assert_eq!(user.id, SteamId::Id(76561198390019021u64));
+        assert_eq!(user.id, ProfileId::Id("76561198390019021".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_scrape_steam_user_decoded_names() {
+        const HTML: &str = r##"
+<div id="search_results">
+  <div class="search_row">
+    <div class="avatarMedium"><a href="https://steamcommunity.com/profiles/76561198390019021"><img src="https://avatars.akamai.steamstatic.com/f5c43cf3801a81c5f9f7e2a791a5f6b0b705bcc1_medium.jpg"></a></div>
+    <div class="searchPersonaInfo">
+      <a class="searchPersonaName" href="https://steamcommunity.com/profiles/76561198390019021"> kiel &amp; Jose&#x301; </a><br>
+    </div>
+  </div>
+</div>
+        "##;
+
+        let (users, _) = scrape_steam_users(HTML, Some).unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].name, "kiel & Jos\u{e9}");
     }
 }
@@ -0,0 +1,291 @@
+use crate::{
+    config::SteamLeaderboardConfig,
+    matching::name_key,
+    scrape::{normalize_name, ScrapeReport, SkippedRow},
+    steam::{Steam, LEADERBOARD_ENTRIES_BATCH},
+    Entry, Error, Result,
+};
+use serde::Serialize;
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// Where the leaderboard is read from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LeaderboardSource {
+    /// The HTML leaderboard on linewar.com.
+    Html,
+    /// Line War's leaderboard in the Steam Web API.
+    Steam,
+}
+
+impl FromStr for LeaderboardSource {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "html" => Ok(Self::Html),
+            "steam" => Ok(Self::Steam),
+            _ => Err(Error::ParseError(
+                format!("unknown leaderboard source {value:?}").into(),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LeaderboardSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Html => f.write_str("html"),
+            Self::Steam => f.write_str("steam"),
+        }
+    }
+}
+
+/// Reads the leaderboard from the Steam Web API. Names and avatars come from each player's
+/// profile, and entries whose profile Steam does not return are skipped. Steam leaderboards
+/// only keep an integer score, so wins and losses are zero and ratings are only as precise
+/// as `score_scale` allows. The entries are for reconciliation and association by Steam ID,
+/// so they are stored apart from the leaderboard history.
+pub async fn fetch_steam_leaderboard(
+    steam: &Steam,
+    config: &SteamLeaderboardConfig,
+) -> Result<(Vec<Entry>, ScrapeReport)> {
+    let app_id = config
+        .app_id
+        .ok_or_else(|| Error::LeaderboardUnavailable("no Steam app ID configured".into()))?;
+    let leaderboard = steam
+        .get_leaderboards_for_game(app_id)
+        .await?
+        .into_iter()
+        .find(|leaderboard| leaderboard.name.eq_ignore_ascii_case(config.name.as_str()))
+        .ok_or_else(|| {
+            Error::LeaderboardUnavailable(format!("no leaderboard named {:?}", config.name))
+        })?;
+    let mut report = ScrapeReport::default();
+    let mut ranked = Vec::new();
+    let mut start = 1;
+
+    loop {
+        let end = start + LEADERBOARD_ENTRIES_BATCH - 1;
+        let mut batch = steam
+            .get_leaderboard_entries(app_id, leaderboard.id, start, end)
+            .await?;
+
+        report.pages_seen.push(report.pages_seen.len() as u32 + 1);
+
+        if batch.entries.is_empty() {
+            break;
+        }
+
+        ranked.append(&mut batch.entries);
+
+        if end >= batch.total {
+            break;
+        }
+
+        start = end + 1;
+    }
+
+    let steam_ids: Vec<_> = ranked.iter().map(|entry| entry.steam_id).collect();
+    let profiles: HashMap<_, _> = steam
+        .get_player_summaries(steam_ids.as_slice())
+        .await?
+        .into_iter()
+        .map(|summary| (summary.steamid, summary))
+        .collect();
+    let mut entries = Vec::with_capacity(ranked.len());
+
+    for (index, ranked) in ranked.into_iter().enumerate() {
+        let profile = match profiles.get(&ranked.steam_id) {
+            Some(profile) => profile,
+            None => {
+                report.rows_skipped.push(SkippedRow {
+                    page: None,
+                    row: index + 1,
+                    reason: format!("no Steam profile for {}", ranked.steam_id),
+                });
+                continue;
+            }
+        };
+
+        entries.push(Entry {
+            rank: ranked.rank,
+            avatar: profile.avatarmedium.clone(),
+            name: normalize_name(profile.personaname.as_str()),
+            rating: ranked.score as f64 / config.score_scale,
+            wins: 0,
+            losses: 0,
+            steam_id: Some(ranked.steam_id),
+        });
+    }
+
+    report.rows_parsed = entries.len();
+
+    Ok((entries, report))
+}
+
+/// Differences between the HTML and Steam leaderboards, with players matched by name.
+#[derive(Debug, Default, Serialize)]
+pub struct ReconcileReport {
+    pub matched: usize,
+    pub html_only: Vec<String>,
+    pub steam_only: Vec<String>,
+    pub differences: Vec<EntryDifference>,
+}
+
+/// A player on both leaderboards whose rank or rating differs.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct EntryDifference {
+    pub name: String,
+    pub html_rank: i32,
+    pub steam_rank: i32,
+    pub html_rating: f64,
+    pub steam_rating: f64,
+}
+
+impl ReconcileReport {
+    pub fn is_consistent(&self) -> bool {
+        self.html_only.is_empty() && self.steam_only.is_empty() && self.differences.is_empty()
+    }
+}
+
+/// Compares the two leaderboards, counting ratings within `rating_tolerance` as equal.
+pub fn reconcile(html: &[Entry], steam: &[Entry], rating_tolerance: f64) -> ReconcileReport {
+    let mut unmatched: HashMap<String, Vec<&Entry>> = HashMap::new();
+    let mut report = ReconcileReport::default();
+
+    for entry in steam.iter().rev() {
+        unmatched
+            .entry(name_key(entry.name.as_str()))
+            .or_default()
+            .push(entry);
+    }
+
+    for entry in html.iter() {
+        let other = match unmatched
+            .get_mut(name_key(entry.name.as_str()).as_str())
+            .and_then(Vec::pop)
+        {
+            Some(other) => other,
+            None => {
+                report.html_only.push(entry.name.clone());
+                continue;
+            }
+        };
+
+        report.matched += 1;
+
+        if entry.rank != other.rank || (entry.rating - other.rating).abs() > rating_tolerance {
+            report.differences.push(EntryDifference {
+                name: entry.name.clone(),
+                html_rank: entry.rank,
+                steam_rank: other.rank,
+                html_rating: entry.rating,
+                steam_rating: other.rating,
+            });
+        }
+    }
+
+    let mut steam_only: Vec<_> = unmatched.into_values().flatten().collect();
+    steam_only.sort_by_key(|entry| entry.rank);
+    report.steam_only = steam_only
+        .into_iter()
+        .map(|entry| entry.name.clone())
+        .collect();
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fetch_steam_leaderboard, reconcile, EntryDifference, LeaderboardSource};
+    use crate::{config::SteamLeaderboardConfig, tests::fixture_transport, Entry, Error, Steam};
+
+    fn entry(rank: i32, name: &str, rating: f64) -> Entry {
+        Entry {
+            rank,
+            avatar: String::new(),
+            name: name.into(),
+            rating,
+            wins: 0,
+            losses: 0,
+            steam_id: None,
+        }
+    }
+
+    fn config() -> SteamLeaderboardConfig {
+        SteamLeaderboardConfig {
+            app_id: Some(480),
+            ..SteamLeaderboardConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_steam_leaderboard() {
+        let steam = Steam::with_transport(fixture_transport()).with_key(Some("key".into()));
+        let (entries, report) = fetch_steam_leaderboard(&steam, &config()).await.unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "monjardin");
+        assert_eq!(entries[0].rating, 49.86);
+        assert_eq!(entries[0].steam_id.unwrap().as_u64(), 76561198020520825);
+        assert!(entries[0].avatar.ends_with("_medium.jpg"));
+        assert_eq!(entries[1].rank, 2);
+        assert_eq!(report.rows_parsed, 2);
+        assert_eq!(report.pages_seen, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_steam_leaderboard_unavailable() {
+        let steam = Steam::with_transport(fixture_transport()).with_key(Some("key".into()));
+        let missing_name = SteamLeaderboardConfig {
+            name: "seasonal".into(),
+            ..config()
+        };
+
+        for config in [SteamLeaderboardConfig::default(), missing_name] {
+            let error = fetch_steam_leaderboard(&steam, &config).await.unwrap_err();
+            assert!(matches!(error, Error::LeaderboardUnavailable(_)));
+        }
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let html = [
+            entry(1, "Orbnet", 49.86),
+            entry(2, "monjardin", 48.5),
+            entry(3, "html only", 40.0),
+        ];
+        let steam = [
+            entry(1, "orbnet", 49.864),
+            entry(2, "steam only", 48.9),
+            entry(3, "monjardin", 48.5),
+        ];
+        let report = reconcile(&html, &steam, 0.01);
+
+        assert_eq!(report.matched, 2);
+        assert_eq!(report.html_only, vec!["html only"]);
+        assert_eq!(report.steam_only, vec!["steam only"]);
+        assert_eq!(
+            report.differences,
+            vec![EntryDifference {
+                name: "monjardin".into(),
+                html_rank: 2,
+                steam_rank: 3,
+                html_rating: 48.5,
+                steam_rating: 48.5,
+            }]
+        );
+        assert!(!report.is_consistent());
+        assert!(reconcile(&html, &html, 0.0).is_consistent());
+    }
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(
+            "HTML".parse::<LeaderboardSource>().unwrap(),
+            LeaderboardSource::Html
+        );
+        assert_eq!(LeaderboardSource::Steam.to_string(), "steam");
+        assert!("csv".parse::<LeaderboardSource>().is_err());
+    }
+}
@@ -1,70 +1,207 @@
 use crate::{
-    fetch::USER_AGENT, parse_avatar_url, scrape::scrape_steam_users, Error, Result, SteamId,
-    SteamUser,
+    config::{EndpointConfig, ScrapeConfig},
+    default_transport,
+    matching::{rank_candidates, Candidate, MatchWeights, DEFAULT_AVATAR_HASHES},
+    parse_avatar_url,
+    scrape::{normalize_name, HtmlScraper, ScrapeReport},
+    transport::{Request, Transport},
+    Error, ProfileId, Result, SteamId, SteamUser,
 };
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use std::{collections::HashSet, sync::Arc};
+use tokio::sync::Mutex;
 use tracing::info;
 
+/// Characters escaped in a URL path segment: the WHATWG path set plus `/` and `%`.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}')
+    .add(b'/')
+    .add(b'%');
+
+/// Largest number of Steam IDs `GetPlayerSummaries` accepts in one request.
+pub const PLAYER_SUMMARIES_BATCH: usize = 100;
+
 pub struct Steam {
-    client: reqwest::Client,
-    key: String,
-    session_id: Option<String>,
+    transport: Arc<dyn Transport>,
+    scraper: HtmlScraper,
+    endpoints: EndpointConfig,
+    weights: MatchWeights,
+    shared_avatars: HashSet<String>,
+    /// Steam Web API key; without one, vanity URLs resolve through the community site.
+    key: Option<String>,
+    /// Community session, started by the first search and replaced when Steam rejects it.
+    session_id: Mutex<Option<String>>,
 }
 
 impl Steam {
     pub fn new() -> Result<Self> {
-        let client = reqwest::Client::builder()
-            .user_agent(USER_AGENT)
-            .cookie_store(true)
-            .build()?;
-        let key = std::env::var("STEAM_API_KEY").map_err(|_| Error::SteamApiKeyNotSet)?;
-        let session_id = None;
-
-        Ok(Steam {
-            client,
+        Self::from_env(Arc::new(default_transport()?))
+    }
+
+    /// Configures a client from the environment like `new`, sending requests through
+    /// `transport`.
+    pub fn from_env(transport: Arc<dyn Transport>) -> Result<Self> {
+        let key = std::env::var("STEAM_API_KEY").ok();
+        let config = ScrapeConfig::from_env()?;
+
+        Ok(Self::with_transport(transport)
+            .with_key(key)
+            .with_scraper(HtmlScraper::new(&config)?)
+            .with_endpoints(config.endpoints))
+    }
+
+    /// Builds a client with the default endpoints and settings, sending requests through
+    /// `transport`. Nothing is read from the environment.
+    pub fn with_transport(transport: Arc<dyn Transport>) -> Self {
+        let scraper = HtmlScraper::default();
+        let endpoints = EndpointConfig::default();
+        let weights = MatchWeights::default();
+        let shared_avatars = DEFAULT_AVATAR_HASHES
+            .iter()
+            .map(|hash| hash.to_string())
+            .collect();
+        let key = None;
+        let session_id = Mutex::new(None);
+
+        Steam {
+            transport,
+            scraper,
+            endpoints,
+            weights,
+            shared_avatars,
             key,
             session_id,
-        })
+        }
     }
 
-    pub async fn start_session(&mut self) -> Result<()> {
-        const URL: &str = "https://steamcommunity.com/search/users";
-        let response = self.client.get(URL).send().await.map_err(Error::from)?;
-        let cookie = response
-            .cookies()
-            .find(|cookie| cookie.name() == "sessionid")
-            .ok_or_else(|| Error::SessionIdNotFound)?;
-        let session_id = cookie.value().to_string();
+    pub fn with_key(self, key: Option<String>) -> Self {
+        Self { key, ..self }
+    }
 
-        info!("sessionid = '{session_id}'");
-        self.session_id.replace(session_id);
+    pub fn with_scraper(self, scraper: HtmlScraper) -> Self {
+        Self { scraper, ..self }
+    }
+
+    pub fn with_endpoints(self, endpoints: EndpointConfig) -> Self {
+        Self { endpoints, ..self }
+    }
+
+    pub fn with_match_weights(self, weights: MatchWeights) -> Self {
+        Self { weights, ..self }
+    }
+
+    pub fn match_weights(&self) -> &MatchWeights {
+        &self.weights
+    }
+
+    /// Adds avatar hashes that too many accounts share to count as evidence of identity.
+    pub fn with_shared_avatars(mut self, hashes: impl IntoIterator<Item = String>) -> Self {
+        self.shared_avatars.extend(hashes);
+        self
+    }
+
+    /// Whether too many accounts use an avatar for it to identify a player.
+    pub fn is_shared_avatar(&self, avatar_hash: &str) -> bool {
+        self.shared_avatars.contains(avatar_hash)
+    }
+
+    /// Starts a new community session, replacing the current one. Searches start a session
+    /// on their own, so this is only needed to fail early when Steam is unreachable.
+    pub async fn start_session(&self) -> Result<()> {
+        let session_id = self.fetch_session_id().await?;
+
+        self.session_id.lock().await.replace(session_id);
 
         Ok(())
     }
 
-    pub async fn search_users(&self, search_text: &str, page: i32) -> Result<UserSearchResponse> {
-        const URL: &str = "https://steamcommunity.com/search/SearchCommunityAjax";
-        let session_id = self
-            .session_id
-            .as_ref()
-            .ok_or_else(|| Error::SessionIdNotFound)?;
+    /// Returns the current session, starting one if there is none or if the current one is
+    /// `stale`. Concurrent callers holding the same stale session share one refresh.
+    async fn session_id(&self, stale: Option<&str>) -> Result<String> {
+        let mut session_id = self.session_id.lock().await;
+
+        match session_id.as_deref() {
+            Some(current) if Some(current) != stale => Ok(current.to_string()),
+            _ => {
+                let fresh = self.fetch_session_id().await?;
+
+                session_id.replace(fresh.clone());
+
+                Ok(fresh)
+            }
+        }
+    }
+
+    async fn fetch_session_id(&self) -> Result<String> {
+        let url = self.endpoints.steam_community_url("search/users");
         let response = self
-            .client
-            .get(URL)
-            .query(&[
-                ("text", search_text),
-                ("filter", "users"),
-                ("page", page.to_string().as_str()),
-                ("sessionid", session_id.as_str()),
-            ])
-            .send()
-            .await
-            .map_err(Error::from)?
+            .transport
+            .get(Request::get(url))
+            .await?
             .error_for_status()?;
+        let session_id = response
+            .cookie("sessionid")
+            .ok_or_else(|| Error::SessionIdNotFound)?
+            .to_string();
 
-        response
-            .json::<UserSearchResponse>()
-            .await
-            .map_err(Error::from)
+        info!("sessionid = '{session_id}'");
+
+        Ok(session_id)
+    }
+
+    /// Searches community users, starting a session if needed and retrying once with a new
+    /// session when Steam rejects the current one.
+    pub async fn search_users(&self, search_text: &str, page: i32) -> Result<UserSearchResponse> {
+        let session_id = self.session_id(None).await?;
+
+        match self.try_search_users(search_text, page, &session_id).await {
+            Err(Error::SessionExpired) => {
+                info!("Steam session expired; starting a new one");
+                let session_id = self.session_id(Some(session_id.as_str())).await?;
+
+                self.try_search_users(search_text, page, &session_id).await
+            }
+            result => result,
+        }
+    }
+
+    async fn try_search_users(
+        &self,
+        search_text: &str,
+        page: i32,
+        session_id: &str,
+    ) -> Result<UserSearchResponse> {
+        let url = self
+            .endpoints
+            .steam_community_url("search/SearchCommunityAjax");
+        let request = Request::get(url)
+            .query("text", search_text)
+            .query("filter", "users")
+            .query("page", page)
+            .query("sessionid", session_id);
+        let response = self.transport.get(request).await?;
+
+        if matches!(response.status, 401 | 403) {
+            return Err(Error::SessionExpired);
+        }
+
+        let response = response.error_for_status()?;
+        let status: SearchStatus = serde_json::from_str(response.body.as_str())?;
+
+        // Steam answers a search with an unknown session with `success` set to 0 or 2.
+        if status.success != 1 {
+            return Err(Error::SessionExpired);
+        }
+
+        serde_json::from_str(response.body.as_str()).map_err(Error::from)
     }
 
     pub async fn find_id_with_avatar(
@@ -72,7 +209,8 @@ impl Steam {
         name: &str,
         avatar_hash: &str,
         depth: i32,
-    ) -> Result<SteamId> {
+    ) -> Result<ProfileId> {
+        let name = normalize_name(name);
         let filter_map = |user: SteamUser| {
             if user.name != name {
                 return None;
@@ -88,33 +226,94 @@ impl Steam {
         };
 
         for page in 0..depth {
-            let response = self.search_users(name, page).await?;
+            let response = self.search_users(name.as_str(), page).await?;
 
             if response.search_result_count == 0 {
                 return Err(Error::UserNotFound);
-            } else if let Ok(user) = scrape_steam_users(response.html.as_str(), filter_map)
-                .and_then(|list| list.into_iter().next().ok_or(Error::UserNotFound))
-            {
-                return Ok(user);
+            }
+
+            let (mut list, report) = self
+                .scraper
+                .scrape_steam_users(response.html.as_str(), filter_map)?;
+
+            log_search_report(name.as_str(), page, &report);
+
+            list.sort_unstable();
+            list.dedup();
+
+            // A shared avatar does not tell its users apart, so even one match is a guess.
+            match list.len() {
+                0 => continue,
+                1 if !self.is_shared_avatar(avatar_hash) => return Ok(list.remove(0)),
+                n => return Err(Error::AmbiguousMatch(n)),
             }
         }
 
         Err(Error::SearchAborted)
     }
 
-    pub async fn resolve_id(&self, vanityurl: &str) -> Result<u64> {
-        const URL: &str = "http://api.steampowered.com/ISteamUser/ResolveVanityURL/v0001";
-        let response = self
-            .client
-            .get(URL)
-            .query(&[("key", self.key.as_str()), ("vanityurl", vanityurl)])
-            .send()
-            .await
-            .map_err(Error::from)?
-            .error_for_status()?;
-        let response = response
-            .json::<ResolvedIdResponse>()
-            .await
+    /// Collects the users found in up to `depth` search pages and ranks them against the
+    /// player's name and avatar.
+    pub async fn find_candidates(
+        &self,
+        name: &str,
+        avatar_hash: &str,
+        depth: i32,
+    ) -> Result<Vec<Candidate>> {
+        let search_text = normalize_name(name);
+        let mut users: Vec<SteamUser> = Vec::new();
+
+        for page in 0..depth {
+            let response = self.search_users(search_text.as_str(), page).await?;
+
+            if response.search_result_count == 0 {
+                break;
+            }
+
+            let (found, report) = self
+                .scraper
+                .scrape_steam_users(response.html.as_str(), Some)?;
+
+            log_search_report(search_text.as_str(), page, &report);
+            let count = users.len();
+
+            for user in found {
+                if users.iter().all(|known| known.id != user.id) {
+                    users.push(user);
+                }
+            }
+
+            if users.len() == count || users.len() >= response.search_result_count as usize {
+                break;
+            }
+        }
+
+        let shared_avatar = self.is_shared_avatar(avatar_hash);
+
+        Ok(rank_candidates(
+            users,
+            name,
+            avatar_hash,
+            shared_avatar,
+            &self.weights,
+        ))
+    }
+
+    /// Resolves a custom profile URL with the Web API when there is a key, or with the
+    /// profile XML otherwise.
+    pub async fn resolve_id(&self, vanityurl: &str) -> Result<SteamId> {
+        let key = match self.key.as_deref() {
+            Some(key) => key,
+            None => return self.resolve_id_from_profile(vanityurl).await,
+        };
+        let url = self
+            .endpoints
+            .steam_api_url("ISteamUser/ResolveVanityURL/v0001");
+        let request = Request::get(url)
+            .query("key", key)
+            .query("vanityurl", vanityurl);
+        let response = self.transport.get(request).await?.error_for_status()?;
+        let response = serde_json::from_str::<ResolvedIdResponse>(response.body.as_str())
             .map_err(Error::from)?
             .response;
 
@@ -124,6 +323,254 @@ impl Steam {
             Err(Error::UserNotFound)
         }
     }
+
+    async fn resolve_id_from_profile(&self, vanityurl: &str) -> Result<SteamId> {
+        let vanityurl = utf8_percent_encode(vanityurl, PATH_SEGMENT);
+        let url = self
+            .endpoints
+            .steam_community_url(format!("id/{vanityurl}").as_str());
+        let request = Request::get(url).query("xml", 1);
+        let response = self.transport.get(request).await?.error_for_status()?;
+
+        parse_profile_xml(response.body.as_str())
+    }
+
+    /// Checks whether an account owns a game and how long it has been played. Steam only
+    /// lists the games of accounts whose library is public.
+    pub async fn get_ownership(&self, steam_id: SteamId, app_id: u32) -> Result<Ownership> {
+        let key = self.key.as_deref().ok_or(Error::SteamApiKeyNotSet)?;
+        let url = self
+            .endpoints
+            .steam_api_url("IPlayerService/GetOwnedGames/v0001");
+        let request = Request::get(url)
+            .query("key", key)
+            .query("steamid", steam_id)
+            .query("include_played_free_games", 1)
+            .query("appids_filter[0]", app_id);
+        let response = self.transport.get(request).await?.error_for_status()?;
+        let games = serde_json::from_str::<OwnedGamesResponse>(response.body.as_str())
+            .map_err(Error::from)?
+            .response;
+
+        if games.game_count.is_none() {
+            return Ok(Ownership::Hidden);
+        }
+
+        Ok(games
+            .games
+            .into_iter()
+            .find(|game| game.appid == app_id)
+            .map(|game| Ownership::Owned {
+                playtime_minutes: game.playtime_forever,
+            })
+            .unwrap_or(Ownership::NotOwned))
+    }
+
+    /// Lists the Steam leaderboards of a game.
+    pub async fn get_leaderboards_for_game(&self, app_id: u32) -> Result<Vec<SteamLeaderboard>> {
+        let key = self.key.as_deref().ok_or(Error::SteamApiKeyNotSet)?;
+        let url = self
+            .endpoints
+            .steam_api_url("ISteamUserStats/GetLeaderboardsForGame/v2");
+        let request = Request::get(url).query("key", key).query("appid", app_id);
+        let response = self.transport.get(request).await?.error_for_status()?;
+        let response = serde_json::from_str::<LeaderboardsResponse>(response.body.as_str())
+            .map_err(Error::from)?
+            .response;
+
+        if response.result != 1 {
+            return Err(Error::LeaderboardUnavailable(format!(
+                "no leaderboards for app {app_id} (result {})",
+                response.result
+            )));
+        }
+
+        Ok(response.leaderboards)
+    }
+
+    /// Fetches the global ranks `start..=end` of a leaderboard.
+    pub async fn get_leaderboard_entries(
+        &self,
+        app_id: u32,
+        leaderboard_id: i64,
+        start: u32,
+        end: u32,
+    ) -> Result<SteamLeaderboardEntries> {
+        let key = self.key.as_deref().ok_or(Error::SteamApiKeyNotSet)?;
+        let url = self
+            .endpoints
+            .steam_api_url("ISteamUserStats/GetLeaderboardEntries/v1");
+        let request = Request::get(url)
+            .query("key", key)
+            .query("appid", app_id)
+            .query("leaderboardid", leaderboard_id)
+            .query("rangestart", start)
+            .query("rangeend", end)
+            .query("datarequest", "RequestGlobal");
+        let response = self.transport.get(request).await?.error_for_status()?;
+
+        serde_json::from_str::<LeaderboardEntriesResponse>(response.body.as_str())
+            .map(|response| response.information)
+            .map_err(Error::from)
+    }
+
+    /// Fetches the public profiles of `steam_ids` in batches of `PLAYER_SUMMARIES_BATCH`.
+    /// Accounts that no longer exist are left out of the result.
+    pub async fn get_player_summaries(&self, steam_ids: &[SteamId]) -> Result<Vec<PlayerSummary>> {
+        let key = self.key.as_deref().ok_or(Error::SteamApiKeyNotSet)?;
+        let url = self
+            .endpoints
+            .steam_api_url("ISteamUser/GetPlayerSummaries/v0002");
+        let mut summaries = Vec::with_capacity(steam_ids.len());
+
+        for batch in steam_ids.chunks(PLAYER_SUMMARIES_BATCH) {
+            let ids: Vec<_> = batch.iter().map(SteamId::to_string).collect();
+            let request = Request::get(url.as_str())
+                .query("key", key)
+                .query("steamids", ids.join(","));
+            let response = self.transport.get(request).await?.error_for_status()?;
+            let mut players =
+                serde_json::from_str::<PlayerSummariesResponse>(response.body.as_str())
+                    .map_err(Error::from)?
+                    .response
+                    .players;
+
+            summaries.append(&mut players);
+        }
+
+        Ok(summaries)
+    }
+}
+
+fn log_search_report(name: &str, page: i32, report: &ScrapeReport) {
+    info!(
+        "Steam search for {name:?} page {page}: {} users parsed, {} skipped.",
+        report.rows_parsed,
+        report.rows_skipped.len()
+    );
+}
+
+/// Ranks requested per `GetLeaderboardEntries` call.
+pub const LEADERBOARD_ENTRIES_BATCH: u32 = 1000;
+
+/// Reads the Steam ID from a profile's XML, which only has an error element when there is no
+/// profile at the requested URL.
+fn parse_profile_xml(xml: &str) -> Result<SteamId> {
+    let (_, rest) = xml.split_once("<steamID64>").ok_or(Error::UserNotFound)?;
+    let (id, _) = rest
+        .split_once("</steamID64>")
+        .ok_or_else(|| Error::ParseError("unterminated steamID64 element".into()))?;
+
+    id.trim().parse().map_err(Error::from)
+}
+
+/// Whether an account owns a game, as far as its privacy settings reveal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ownership {
+    Owned {
+        playtime_minutes: u32,
+    },
+    NotOwned,
+    /// The account's game library is not public.
+    Hidden,
+}
+
+impl Ownership {
+    pub fn owns_game(&self) -> Option<bool> {
+        match self {
+            Self::Owned { .. } => Some(true),
+            Self::NotOwned => Some(false),
+            Self::Hidden => None,
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct OwnedGame {
+    appid: u32,
+    playtime_forever: u32,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct OwnedGames {
+    /// Missing when the library is hidden.
+    game_count: Option<u32>,
+    #[serde(default)]
+    games: Vec<OwnedGame>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct OwnedGamesResponse {
+    response: OwnedGames,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SteamLeaderboard {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Leaderboards {
+    result: i32,
+    #[serde(default)]
+    leaderboards: Vec<SteamLeaderboard>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct LeaderboardsResponse {
+    response: Leaderboards,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SteamLeaderboardEntry {
+    #[serde(rename = "steamID")]
+    pub steam_id: SteamId,
+    pub score: i32,
+    pub rank: i32,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SteamLeaderboardEntries {
+    #[serde(rename = "totalLeaderBoardEntryCount")]
+    pub total: u32,
+    #[serde(rename = "leaderboardEntries", default)]
+    pub entries: Vec<SteamLeaderboardEntry>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct LeaderboardEntriesResponse {
+    #[serde(rename = "leaderboardEntryInformation")]
+    information: SteamLeaderboardEntries,
+}
+
+/// Community visibility of a profile that anyone can view.
+pub const VISIBILITY_PUBLIC: i32 = 3;
+
+/// The public parts of a Steam profile returned by `GetPlayerSummaries`.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct PlayerSummary {
+    pub steamid: SteamId,
+    pub personaname: String,
+    pub profileurl: String,
+    /// The 64 pixel avatar, the size the Line War leaderboard shows.
+    pub avatarmedium: String,
+    pub avatarfull: String,
+    /// Only present when the player has set a location.
+    #[serde(default)]
+    pub loccountrycode: Option<String>,
+    /// 1 when the profile is private or friends only, `VISIBILITY_PUBLIC` otherwise.
+    pub communityvisibilitystate: i32,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct PlayerSummaries {
+    players: Vec<PlayerSummary>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct PlayerSummariesResponse {
+    response: PlayerSummaries,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -137,6 +584,11 @@ pub struct ResolvedIdResponse {
     response: ResolvedId,
 }
 
+#[derive(Debug, serde::Deserialize)]
+struct SearchStatus {
+    success: i32,
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct UserSearchResponse {
     pub success: i32,
@@ -149,25 +601,289 @@ pub struct UserSearchResponse {
 
 #[cfg(test)]
 mod test {
-    use super::Steam;
+    use super::{Ownership, Steam, PLAYER_SUMMARIES_BATCH, VISIBILITY_PUBLIC};
+    use crate::{
+        config::EndpointConfig,
+        tests::{fixture_server, fixture_transport},
+        transport::{HttpTransport, Request, Response, Transport},
+        Error, ProfileId, Result, SteamId,
+    };
+    use futures_util::future::BoxFuture;
+    use std::sync::{Arc, Mutex};
+
+    /// Hands out numbered sessions and rejects searches made with the first one.
+    struct ExpiringSessionTransport {
+        sessions: Mutex<u32>,
+        fixtures: Arc<dyn Transport>,
+    }
+
+    impl Transport for ExpiringSessionTransport {
+        fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+            Box::pin(async move {
+                if request.url.ends_with("search/users") {
+                    let mut sessions = self.sessions.lock().unwrap();
+                    *sessions += 1;
+
+                    return Ok(Response {
+                        status: 200,
+                        headers: vec![(
+                            "Set-Cookie".into(),
+                            format!("sessionid=session{sessions}; Path=/"),
+                        )],
+                        body: String::new(),
+                    });
+                }
+
+                let expired = request
+                    .query
+                    .iter()
+                    .any(|(key, value)| key == "sessionid" && value == "session1");
+
+                if expired {
+                    Ok(Response {
+                        status: 200,
+                        headers: Vec::new(),
+                        body: r#"{"success":2}"#.into(),
+                    })
+                } else {
+                    self.fixtures.get(request).await
+                }
+            })
+        }
+    }
+
+    /// Answers every request with the same profile XML and records the requested URLs.
+    struct ProfileTransport {
+        urls: Mutex<Vec<String>>,
+    }
+
+    impl Transport for ProfileTransport {
+        fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+            Box::pin(async move {
+                self.urls.lock().unwrap().push(request.url);
+
+                Ok(Response {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: "<profile><steamID64>76561198020520825</steamID64></profile>".into(),
+                })
+            })
+        }
+    }
+
+    /// Answers `GetPlayerSummaries` with an empty player list and records each batch size.
+    struct BatchTransport {
+        batches: Mutex<Vec<usize>>,
+    }
+
+    impl Transport for BatchTransport {
+        fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+            Box::pin(async move {
+                let ids = request
+                    .query
+                    .iter()
+                    .find(|(key, _)| key == "steamids")
+                    .map(|(_, value)| value.split(',').count())
+                    .unwrap_or_default();
+
+                self.batches.lock().unwrap().push(ids);
+
+                Ok(Response {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: r#"{"response":{"players":[]}}"#.into(),
+                })
+            })
+        }
+    }
+
+    fn steam() -> Steam {
+        let key = std::env::var("STEAM_API_KEY").unwrap_or_default();
+
+        Steam::with_transport(fixture_transport()).with_key(Some(key))
+    }
 
     #[tokio::test]
     async fn test_steam_search_users() {
-        dotenv::dotenv().unwrap();
-        let mut steam = Steam::new().expect("error initializing Steam client");
+        let steam = steam();
+
+        let response = steam.search_users("monjardin", 1).await.unwrap();
+        assert_eq!(response.search_result_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_steam_session_refresh() {
+        let transport = Arc::new(ExpiringSessionTransport {
+            sessions: Mutex::new(0),
+            fixtures: fixture_transport(),
+        });
+        let steam = Steam::with_transport(transport.clone());
+
+        for _ in 0..2 {
+            let response = steam.search_users("monjardin", 1).await.unwrap();
+            assert_eq!(response.search_result_count, 2);
+        }
+
+        assert_eq!(*transport.sessions.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_steam_find_id_with_avatar() {
+        let steam = steam();
 
-        steam
-            .start_session()
+        let steam_id = steam
+            .find_id_with_avatar("monjardin", "c4c2152dfa696da706cd5484dc0d4de10fa062a0", 1)
             .await
-            .expect("failed to fetch the session ID cookie");
-        steam.search_users("monjardin", 1).await.unwrap();
+            .unwrap();
+        assert_eq!(steam_id, ProfileId::Url("monjardin1".into()));
+    }
+
+    #[tokio::test]
+    async fn test_steam_find_id_with_shared_avatar() {
+        const HASH: &str = "c4c2152dfa696da706cd5484dc0d4de10fa062a0";
+        let steam = steam().with_shared_avatars([HASH.to_string()]);
+
+        let result = steam.find_id_with_avatar("monjardin", HASH, 1).await;
+        assert!(
+            matches!(result, Err(Error::AmbiguousMatch(1))),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_steam_find_candidates() {
+        let steam = steam();
+
+        let candidates = steam
+            .find_candidates("monjardin ", "0123456789abcdef0123456789abcdef01234567", 3)
+            .await
+            .unwrap();
+
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].user.id, ProfileId::Url("monjardin1".into()));
+        assert!(!candidates[0].avatar_match);
+        assert!(candidates[0].profile_match);
+        assert!(candidates[0].confidence > candidates[1].confidence);
     }
 
     #[tokio::test]
     async fn test_steam_resolve_id() {
-        dotenv::dotenv().unwrap();
-        let steam = Steam::new().expect("error initializing Steam client");
+        let steam = steam();
+        let steam_id = steam.resolve_id("monjardin1").await.unwrap();
+        assert_eq!(steam_id.as_u64(), 76561198020520825);
+    }
+
+    #[tokio::test]
+    async fn test_steam_resolve_id_without_key() {
+        let steam = Steam::with_transport(fixture_transport());
+
         let steam_id = steam.resolve_id("monjardin1").await.unwrap();
-        assert_eq!(steam_id, 76561198020520825u64);
+        assert_eq!(steam_id.as_u64(), 76561198020520825);
+
+        let error = steam.resolve_id("no-such-vanity-url").await.unwrap_err();
+        assert!(matches!(error, Error::UserNotFound));
+
+        let error = steam.get_player_summaries(&[steam_id]).await.unwrap_err();
+        assert!(matches!(error, Error::SteamApiKeyNotSet));
+    }
+
+    #[tokio::test]
+    async fn test_steam_resolve_id_escapes_vanity_url() {
+        let transport = Arc::new(ProfileTransport {
+            urls: Mutex::new(Vec::new()),
+        });
+        let steam = Steam::with_transport(transport.clone());
+
+        steam.resolve_id("a/b c?d%").await.unwrap();
+
+        let urls = transport.urls.lock().unwrap();
+        assert_eq!(urls.len(), 1);
+        assert!(urls[0].ends_with("/id/a%2Fb%20c%3Fd%25"), "{}", urls[0]);
+    }
+
+    #[tokio::test]
+    async fn test_steam_local_endpoints() {
+        let endpoints = EndpointConfig {
+            steam_community: fixture_server("steamcommunity.com").await,
+            steam_api: fixture_server("api.steampowered.com").await,
+            ..EndpointConfig::default()
+        };
+        let transport = Arc::new(HttpTransport::new().unwrap());
+        let steam = Steam::with_transport(transport)
+            .with_key(Some("key".into()))
+            .with_endpoints(endpoints);
+
+        let steam_id = steam
+            .find_id_with_avatar("monjardin", "c4c2152dfa696da706cd5484dc0d4de10fa062a0", 1)
+            .await
+            .unwrap();
+        assert_eq!(steam_id, ProfileId::Url("monjardin1".into()));
+
+        let steam_id = steam.resolve_id("monjardin1").await.unwrap();
+        assert_eq!(steam_id.as_u64(), 76561198020520825);
+    }
+
+    #[tokio::test]
+    async fn test_steam_player_summaries() {
+        let endpoints = EndpointConfig {
+            steam_api: fixture_server("api.steampowered.com").await,
+            ..EndpointConfig::default()
+        };
+        let transport = Arc::new(HttpTransport::new().unwrap());
+        let steam = Steam::with_transport(transport)
+            .with_key(Some("key".into()))
+            .with_endpoints(endpoints);
+        let steam_ids = [
+            SteamId::new(76561198020520825).unwrap(),
+            SteamId::new(76561197960287930).unwrap(),
+        ];
+
+        let summaries = steam.get_player_summaries(&steam_ids).await.unwrap();
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].steamid, steam_ids[0]);
+        assert_eq!(summaries[0].personaname, "monjardin");
+        assert_eq!(summaries[0].loccountrycode.as_deref(), Some("US"));
+        assert_eq!(summaries[0].communityvisibilitystate, VISIBILITY_PUBLIC);
+        assert_eq!(summaries[1].loccountrycode, None);
+        assert!(summaries[1].avatarfull.ends_with("_full.jpg"));
+    }
+
+    #[tokio::test]
+    async fn test_steam_player_summaries_batches() {
+        let transport = Arc::new(BatchTransport {
+            batches: Mutex::new(Vec::new()),
+        });
+        let steam = Steam::with_transport(transport.clone()).with_key(Some("key".into()));
+        let steam_ids: Vec<_> = (1..=250).map(SteamId::from_account_id).collect();
+
+        steam.get_player_summaries(&steam_ids).await.unwrap();
+
+        assert_eq!(
+            *transport.batches.lock().unwrap(),
+            vec![PLAYER_SUMMARIES_BATCH, PLAYER_SUMMARIES_BATCH, 50]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_steam_ownership() {
+        let steam = Steam::with_transport(fixture_transport()).with_key(Some("key".into()));
+        let cases = [
+            (
+                76561198020520825,
+                Ownership::Owned {
+                    playtime_minutes: 5321,
+                },
+            ),
+            (76561197960287930, Ownership::Hidden),
+            (76561197960287931, Ownership::NotOwned),
+        ];
+
+        for (id, expected) in cases {
+            let steam_id = SteamId::new(id).unwrap();
+            let ownership = steam.get_ownership(steam_id, 480).await.unwrap();
+
+            assert_eq!(ownership, expected);
+        }
     }
 }
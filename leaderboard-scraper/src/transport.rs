@@ -0,0 +1,395 @@
+use crate::{fetch::USER_AGENT, Error, Result};
+use futures_util::future::BoxFuture;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tracing::debug;
+
+/// Query parameters that change between runs and are left out of fixture names.
+const VOLATILE_PARAMETERS: &[&str] = &["key", "sessionid"];
+
+/// Characters escaped in fixture names, which leaves `_` and `=` free to separate the parts.
+const FIXTURE_NAME: &AsciiSet = &NON_ALPHANUMERIC.remove(b'.').remove(b'-');
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub url: String,
+    pub query: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            query: Vec::new(),
+        }
+    }
+
+    pub fn query(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.query.push((key.into(), value.to_string()));
+        self
+    }
+
+    /// Returns a file system safe name that identifies this request in a fixture directory.
+    /// Path segments and query parameters are joined with `_` and percent-encoded, so
+    /// distinct requests never share a name.
+    pub fn fixture_name(&self) -> String {
+        let url = self
+            .url
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .unwrap_or(self.url.as_str());
+        let mut query: Vec<_> = self
+            .query
+            .iter()
+            .filter(|(key, _)| !VOLATILE_PARAMETERS.contains(&key.as_str()))
+            .map(|(key, value)| {
+                format!(
+                    "{}={}",
+                    utf8_percent_encode(key, FIXTURE_NAME),
+                    utf8_percent_encode(value, FIXTURE_NAME)
+                )
+            })
+            .collect();
+        query.sort();
+
+        let mut name: Vec<_> = url
+            .trim_end_matches('/')
+            .split('/')
+            .map(|segment| utf8_percent_encode(segment, FIXTURE_NAME).to_string())
+            .collect();
+        name.append(&mut query);
+
+        format!("{}.json", name.join("_"))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Response {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case("set-cookie"))
+            .filter_map(|(_, value)| value.split(';').next()?.trim().split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Parses `Retry-After` given either as delay seconds or as an HTTP date.
+    pub fn retry_after(&self) -> Option<Duration> {
+        let value = self.header("retry-after")?.trim();
+
+        match value.parse::<u64>() {
+            Ok(seconds) => Some(Duration::from_secs(seconds)),
+            Err(_) => httpdate::parse_http_date(value)
+                .ok()?
+                .duration_since(SystemTime::now())
+                .ok(),
+        }
+    }
+
+    pub fn status_error(&self) -> Option<Error> {
+        match self.status {
+            200..=299 => None,
+            429 => Some(Error::RateLimited(self.retry_after())),
+            status => Some(Error::HttpStatusError(status)),
+        }
+    }
+
+    pub fn error_for_status(self) -> Result<Self> {
+        match self.status_error() {
+            Some(error) => Err(error),
+            None => Ok(self),
+        }
+    }
+}
+
+/// Performs the HTTP requests made by the leaderboard and Steam clients.
+pub trait Transport: Send + Sync {
+    fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>>;
+}
+
+/// Sends requests to the network with `reqwest`.
+pub struct HttpTransport {
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    pub fn new() -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .cookie_store(true)
+            .build()?;
+
+        Ok(Self { client })
+    }
+}
+
+impl Transport for HttpTransport {
+    fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .get(request.url.as_str())
+                .query(&request.query)
+                .send()
+                .await
+                .map_err(Error::from)?;
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect();
+            let body = response.text().await.map_err(Error::from)?;
+
+            Ok(Response {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+/// Responses seen by a `CountingTransport`, counted by HTTP status. Requests that fail
+/// without a response are counted under `error`.
+#[derive(Clone, Debug, Default)]
+pub struct StatusCounts(Arc<Mutex<BTreeMap<String, usize>>>);
+
+impl StatusCounts {
+    pub fn snapshot(&self) -> BTreeMap<String, usize> {
+        self.0.lock().expect("status counts lock poisoned").clone()
+    }
+
+    fn record(&self, key: String) {
+        *self
+            .0
+            .lock()
+            .expect("status counts lock poisoned")
+            .entry(key)
+            .or_default() += 1;
+    }
+}
+
+/// Forwards requests to an inner transport and counts its responses by status. Placed
+/// inside a `PolicyTransport`, it sees every retry.
+pub struct CountingTransport<T> {
+    inner: T,
+    counts: StatusCounts,
+}
+
+impl<T> CountingTransport<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            counts: StatusCounts::default(),
+        }
+    }
+
+    /// Returns a handle that keeps reading the counts after the transport is moved.
+    pub fn counts(&self) -> StatusCounts {
+        self.counts.clone()
+    }
+}
+
+impl<T: Transport> Transport for CountingTransport<T> {
+    fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let result = self.inner.get(request).await;
+            let key = match &result {
+                Ok(response) => response.status.to_string(),
+                Err(_) => "error".to_string(),
+            };
+
+            self.counts.record(key);
+
+            result
+        })
+    }
+}
+
+/// Forwards requests to an inner transport and saves every response to a fixture directory.
+pub struct RecordingTransport<T> {
+    inner: T,
+    dir: PathBuf,
+}
+
+impl<T> RecordingTransport<T> {
+    pub fn new(inner: T, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+        }
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let path = self.dir.join(request.fixture_name());
+            let response = self.inner.get(request).await?;
+            let json = serde_json::to_string_pretty(&response)?;
+
+            debug!("recording fixture {path:?}");
+            tokio::fs::create_dir_all(self.dir.as_path()).await?;
+            tokio::fs::write(path, json).await?;
+
+            Ok(response)
+        })
+    }
+}
+
+/// Answers requests from a fixture directory written by `RecordingTransport`.
+pub struct ReplayTransport {
+    dir: PathBuf,
+}
+
+impl ReplayTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let path = self.dir.join(request.fixture_name());
+
+            debug!("replaying fixture {path:?}");
+            let json = tokio::fs::read_to_string(path.as_path())
+                .await
+                .map_err(|_| Error::FixtureNotFound(path.display().to_string()))?;
+
+            serde_json::from_str(json.as_str()).map_err(Error::from)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        CountingTransport, RecordingTransport, ReplayTransport, Request, Response, Transport,
+    };
+    use crate::Result;
+    use futures_util::future::BoxFuture;
+
+    struct EchoTransport;
+
+    impl Transport for EchoTransport {
+        fn get(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+            Box::pin(async move {
+                Ok(Response {
+                    status: 200,
+                    headers: vec![("Set-Cookie".into(), "sessionid=abc123; Path=/".into())],
+                    body: format!("{request:?}"),
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn test_fixture_name() {
+        let request = Request::get("https://steamcommunity.com/search/SearchCommunityAjax")
+            .query("text", "mon jardin")
+            .query("filter", "users")
+            .query("page", 1)
+            .query("sessionid", "abc123");
+
+        assert_eq!(
+            request.fixture_name(),
+            "steamcommunity.com_search_SearchCommunityAjax_filter=users_page=1_text=mon%20jardin.json"
+        );
+
+        for (a, b) in [
+            (
+                Request::get("a/b").query("q", "a b"),
+                Request::get("a/b").query("q", "a_b"),
+            ),
+            (
+                Request::get("a/b").query("q", "c&d"),
+                Request::get("a/b").query("q", "c/d"),
+            ),
+            (Request::get("a/b_c"), Request::get("a_b/c")),
+            (
+                Request::get("a").query("b", "c=d"),
+                Request::get("a").query("b=c", "d"),
+            ),
+        ] {
+            assert_ne!(a.fixture_name(), b.fixture_name(), "{a:?} / {b:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let dir = std::env::temp_dir().join(format!("linewar-fixtures-{}", std::process::id()));
+        let request = Request::get("https://linewar.com/Leaderboard/Index").query("page", 7);
+
+        let recorder = RecordingTransport::new(EchoTransport, dir.as_path());
+        let recorded = recorder.get(request.clone()).await.unwrap();
+        let replayer = ReplayTransport::new(dir.as_path());
+        let replayed = replayer.get(request).await.unwrap();
+
+        assert_eq!(recorded, replayed);
+        assert_eq!(replayed.cookie("sessionid"), Some("abc123"));
+        assert!(replayer
+            .get(Request::get("https://linewar.com/missing"))
+            .await
+            .is_err());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_count_statuses() {
+        let dir = std::env::temp_dir().join(format!("linewar-counts-{}", std::process::id()));
+        let transport =
+            CountingTransport::new(RecordingTransport::new(EchoTransport, dir.as_path()));
+        let counts = transport.counts();
+
+        transport
+            .get(Request::get("https://linewar.com/Leaderboard/Index"))
+            .await
+            .unwrap();
+        transport
+            .get(Request::get("https://linewar.com/Leaderboard/Index"))
+            .await
+            .unwrap();
+
+        let replayer = CountingTransport::new(ReplayTransport::new(dir.as_path()));
+        let missing = replayer.counts();
+        replayer
+            .get(Request::get("https://linewar.com/missing"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            counts.snapshot().into_iter().collect::<Vec<_>>(),
+            vec![("200".to_string(), 2)]
+        );
+        assert_eq!(missing.snapshot().get("error"), Some(&1));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
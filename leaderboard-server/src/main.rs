@@ -9,9 +9,10 @@ use axum::{
 };
 use leaderboard_db::{
     models::RecentLeaderboard,
-    service::{DatabaseService, Leaderboard, Player},
+    service::{DatabaseService, JobHistory, Leaderboard, Player},
 };
 use std::net::SocketAddr;
+use steam_id::SteamId;
 use timeago::TimeUnit;
 use tokio::sync::oneshot;
 use tower_http::trace::TraceLayer;
@@ -29,6 +30,7 @@ async fn main() {
         .route("/recent", get(recent))
         .route("/player/:steam_id", get(player))
         .route("/plot/rating/:steam_id", get(plot_rating))
+        .route("/status", get(status))
         .layer(TraceLayer::new_for_http())
         .layer(Extension(service));
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
@@ -136,24 +138,23 @@ struct LeaderboardEntry {
     pub recent_rank: i32,
     pub overall_rank: i32,
     pub name: String,
-    pub rating: f32,
+    pub rating: f64,
     pub wins: i32,
     pub losses: i32,
-    pub steam_id: u64,
+    pub steam_id: SteamId,
     pub time_ago: String,
 }
 
 impl From<(usize, RecentLeaderboard)> for LeaderboardEntry {
     fn from(value: (usize, RecentLeaderboard)) -> Self {
-        let steam_id = value.1.get_steam_id();
         let RecentLeaderboard {
             rank,
             name,
             rating,
             wins,
             losses,
+            steam_id,
             last_at,
-            ..
         } = value.1;
         let recent_rank = value.0 as i32 + 1;
         let overall_rank = rank;
@@ -177,8 +178,9 @@ impl From<(usize, RecentLeaderboard)> for LeaderboardEntry {
 #[tracing::instrument(skip(services))]
 async fn player(
     Extension(services): Extension<Services>,
-    Path(steam_id): Path<u64>,
+    Path(steam_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let steam_id = parse_steam_id(steam_id.as_str())?;
     let response = services
         .cache
         .get_cached(format!("player/{steam_id}").as_str(), || {
@@ -199,8 +201,9 @@ async fn player(
 #[tracing::instrument(skip(services))]
 async fn plot_rating(
     Extension(services): Extension<Services>,
-    Path(steam_id): Path<u64>,
+    Path(steam_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let steam_id = parse_steam_id(steam_id.as_str())?;
     let response = services
         .cache
         .get_cached(format!("plot/rating/{steam_id}").as_str(), || {
@@ -228,22 +231,22 @@ async fn plot_rating(
                             .margin(20)
                             .x_label_area_size(30)
                             .y_label_area_size(30)
-                            .build_cartesian_2d(x_min..x_max, 0f32..50f32)
+                            .build_cartesian_2d(x_min..x_max, 0f64..50f64)
                             .unwrap();
 
                         chart
                             .configure_mesh()
-                            .x_label_formatter(&|x| format!("{}", x.date().naive_utc()))
+                            .x_label_formatter(&|x| format!("{}", x.naive_utc().date()))
                             .draw()
                             .unwrap();
                         chart
                             .draw_series(
                                 AreaSeries::new(
                                     context.history.iter().map(|i| (i.timestamp, i.rating)),
-                                    0f32,
-                                    &BLUE.mix(0.2),
+                                    0f64,
+                                    BLUE.mix(0.2),
                                 )
-                                .border_style(&BLUE),
+                                .border_style(BLUE),
                             )
                             .unwrap();
                     }
@@ -268,6 +271,54 @@ struct PlayerTemplate {
     context: Player,
 }
 
+/// Number of runs of each job listed on the status page.
+const JOB_HISTORY_LIMIT: i64 = 50;
+
+/// Not cached, so operators see runs as soon as they finish.
+#[tracing::instrument(skip(services))]
+async fn status(
+    Extension(services): Extension<Services>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let context = services
+        .db
+        .get_job_history(JOB_HISTORY_LIMIT)
+        .await
+        .map_err(into_error_response)?;
+    let response = StatusTemplate { context }
+        .render()
+        .map_err(into_error_response)?;
+
+    Ok(Html(response))
+}
+
+#[derive(Template)]
+#[template(path = "status.html")]
+struct StatusTemplate {
+    context: JobHistory,
+}
+
+mod filters {
+    use std::fmt::Display;
+
+    /// Shows a value that is missing for older or unfinished runs.
+    pub fn or_blank<T: Display>(value: &Option<T>) -> askama::Result<String> {
+        Ok(value.as_ref().map(ToString::to_string).unwrap_or_default())
+    }
+
+    pub fn seconds(value: &Option<f64>) -> askama::Result<String> {
+        Ok(value
+            .map(|seconds| format!("{seconds:.1}"))
+            .unwrap_or_default())
+    }
+}
+
+/// Accepts a SteamID64, `STEAM_0:X:Y` or `[U:1:Z]`.
+fn parse_steam_id(value: &str) -> Result<SteamId, (StatusCode, String)> {
+    value
+        .parse()
+        .map_err(|error| (StatusCode::BAD_REQUEST, format!("{error}")))
+}
+
 fn into_error_response<E>(error: E) -> (StatusCode, String)
 where
     E: std::fmt::Debug,
@@ -1,76 +1,281 @@
 use clap::Parser;
 use dotenv::dotenv;
-use leaderboard_db::LeaderboardDatabase;
-use leaderboard_scraper::{Steam, SteamId};
+use leaderboard_db::{
+    models::{AssociationJobRun, JobStatus},
+    LeaderboardDatabase,
+};
+use leaderboard_scraper::{
+    config::ScrapeConfig,
+    matching::{decide, sort_candidates, Candidate, Decision},
+    parse_avatar_url,
+    steam::Ownership,
+    Error, ProfileId, Result, Steam, SteamId, VERSION,
+};
+use std::time::{Duration, SystemTime};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
     #[clap(short, long, default_value_t = 1)]
     search_depth: i32,
+    /// Lowest confidence at which the best candidate is associated without review
+    #[clap(short, long, default_value_t = 0.85)]
+    threshold: f64,
+    /// Number of different names using an avatar before it no longer identifies a player
+    #[clap(long, default_value_t = 3)]
+    shared_avatar_names: i64,
+    /// Check whether likely candidates own Line War and use the answer as evidence
+    #[clap(long)]
+    verify_ownership: bool,
+    /// First associate the players of the latest Steam leaderboard scrape, which lists
+    /// Steam IDs
+    #[clap(long)]
+    steam_leaderboard: bool,
 }
 
 #[tokio::main]
 async fn main() {
     let (db, steam) = init().await;
     let args = Args::parse();
+    let job = db
+        .start_association_job(VERSION)
+        .expect("failed to create new association job");
+    let mut run = AssociationJobRun::default();
+    let result = associate(&db, steam, &args, &mut run).await;
+    let status = match &result {
+        Ok(()) => JobStatus::Complete,
+        Err(error) => {
+            run.error = Some(error.to_string());
+            JobStatus::Failed
+        }
+    };
+
+    db.finish_association_job(job.id, status, &run)
+        .expect("failed to record the association job");
 
-    let n = db.index_names().expect("error indexing new names");
+    if let Err(error) = result {
+        eprintln!("Association job {} failed: {error}", job.id);
+        std::process::exit(1);
+    }
+}
+
+/// Runs the association pipeline, keeping its counts in `run`.
+async fn associate(
+    db: &LeaderboardDatabase,
+    steam: Steam,
+    args: &Args,
+    run: &mut AssociationJobRun,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let n = db.index_names()?;
     println!("Indexed {n} new names.");
+    run.names_indexed = n as i32;
 
-    let n = db
-        .hash_avatar_urls()
-        .expect("error hashing new avatar URLs");
+    let n = db.hash_avatar_urls()?;
     println!("Hashed {n} new avatar URLs.");
+    run.avatars_hashed = n as i32;
+
+    let shared_avatars = db.get_shared_avatar_hashes(args.shared_avatar_names)?;
+    println!("Found {} shared avatars.", shared_avatars.len());
+    let steam = steam.with_shared_avatars(shared_avatars);
+    let app_id =
+        if args.verify_ownership {
+            let config = ScrapeConfig::from_env()?;
+
+            Some(config.steam_leaderboard.app_id.ok_or(
+                "verifying ownership requires steam_leaderboard.app_id in the scraper config",
+            )?)
+        } else {
+            None
+        };
+
+    if args.steam_leaderboard {
+        let entries = db.get_steam_leaderboard()?;
+        let players: Vec<_> = entries
+            .iter()
+            .filter_map(|(name, avatar, steam_id)| {
+                let avatar_hash = parse_avatar_url(avatar.as_str())?;
+
+                Some((name.as_str(), avatar_hash, *steam_id))
+            })
+            .collect();
+        let n = db.associate_known_players(players.as_slice())?;
+        println!(
+            "Associated {n} players from {} Steam leaderboard entries.",
+            entries.len()
+        );
+        run.players_associated += n as i32;
+    }
 
-    let new_players = db.get_new_players().expect("error querying new players");
+    let new_players = db.get_new_players()?;
     println!("Found {} unassociated players", new_players.len());
+    run.players_found = new_players.len() as i32;
 
     for player in new_players.into_iter() {
         let (name, avatar_hash, names_id, avatar_hash_id) = player;
-        let result = match steam
-            .find_id_with_avatar(&name, &avatar_hash, args.search_depth)
+        let mut candidates = match steam
+            .find_candidates(&name, &avatar_hash, args.search_depth)
             .await
         {
-            Ok(steam_id) => match steam_id {
-                SteamId::Id(value) => Ok(value),
-                SteamId::Url(value) => steam.resolve_id(value.as_str()).await,
+            Ok(candidates) => candidates,
+            Err(error) => {
+                eprintln!("{name} / {avatar_hash} => {error:?}");
+                run.lookup_failures += 1;
+                continue;
+            }
+        };
+
+        if let Some(app_id) = app_id {
+            verify_ownership(db, &steam, &mut candidates, app_id, args.threshold).await;
+        }
+
+        let best = match decide(&candidates, args.threshold) {
+            Decision::Accept(best) => best,
+            Decision::Ambiguous(reason) => {
+                println!("Ambiguous: {name} / {avatar_hash} ({reason})");
+                queue_review(db, names_id, avatar_hash_id, &candidates, true);
+                run.players_queued += 1;
+                continue;
+            }
+            Decision::Review => {
+                println!(
+                    "Queued for review: {name} / {avatar_hash} (best confidence {:.2})",
+                    candidates[0].confidence
+                );
+                queue_review(db, names_id, avatar_hash_id, &candidates, false);
+                run.players_queued += 1;
+                continue;
+            }
+            Decision::NotFound => {
+                eprintln!("{name} / {avatar_hash} => {:?}", Error::UserNotFound);
+                run.lookup_failures += 1;
+                continue;
+            }
+        };
+        let result = match &best.user.id {
+            ProfileId::Id(value) => Ok(*value),
+            ProfileId::Url(value) => resolve_vanity_url(db, &steam, value.as_str()).await,
+        };
+
+        match result {
+            Ok(steam_id) => match db.associate_player(names_id, avatar_hash_id, steam_id) {
+                Ok(n) => {
+                    println!("Associated new player({n}): {name} / {avatar_hash} / {steam_id}");
+                    run.players_associated += 1;
+                }
+                Err(error) => eprintln!(
+                    "Error associating player: {name} / {avatar_hash} / {steam_id} / {error:?}"
+                ),
             },
+            Err(error) => {
+                eprintln!("{name} / {avatar_hash} => {error:?}");
+                run.lookup_failures += 1;
+            }
+        }
+    }
+
+    let n = db.associate_leaderboard()?;
+    println!("Associated {n} leaderboard entries with players.");
+    run.entries_associated = n as i32;
+
+    Ok(())
+}
+
+/// Checks the libraries of the candidates whose ownership could change the decision and
+/// records the playtime of those that own the game.
+async fn verify_ownership(
+    db: &LeaderboardDatabase,
+    steam: &Steam,
+    candidates: &mut [Candidate],
+    app_id: u32,
+    threshold: f64,
+) {
+    let weights = steam.match_weights().clone();
+
+    for candidate in candidates.iter_mut() {
+        let reachable = candidate.confidence + weights.ownership * (1.0 - candidate.confidence);
+        let shared_exact =
+            candidate.shared_avatar && candidate.avatar_match && candidate.name_similarity == 1.0;
+
+        if reachable < threshold && !shared_exact {
+            continue;
+        }
+
+        let steam_id = match &candidate.user.id {
+            ProfileId::Id(value) => Ok(*value),
+            ProfileId::Url(value) => resolve_vanity_url(db, steam, value.as_str()).await,
+        };
+        let ownership = match steam_id {
+            Ok(steam_id) => steam
+                .get_ownership(steam_id, app_id)
+                .await
+                .map(|ownership| (steam_id, ownership)),
             Err(error) => Err(error),
         };
 
-        match result {
-            Ok(steam_id) => {
-                match db.associate_player(names_id, avatar_hash_id, steam_id.to_le_bytes().into()) {
-                    Ok(n) => {
-                        println!("Associated new player({n}): {name} / {avatar_hash} / {steam_id}")
+        match ownership {
+            Ok((steam_id, ownership)) => {
+                if let Ownership::Owned { playtime_minutes } = ownership {
+                    if let Err(error) = db.store_playtime(steam_id, playtime_minutes as i32) {
+                        eprintln!("Error storing playtime: {steam_id} / {error:?}");
                     }
-                    Err(error) => eprintln!(
-                        "Error associating player: {name} / {avatar_hash} / {steam_id} / {error:?}"
-                    ),
                 }
+
+                candidate.verify_ownership(ownership.owns_game(), &weights);
             }
-            Err(error) => eprintln!("{name} / {avatar_hash} => {error:?}"),
+            Err(error) => eprintln!(
+                "Error verifying ownership: {:?} / {error:?}",
+                candidate.user.id
+            ),
         }
     }
 
-    let n = db
-        .associate_leaderboard()
-        .expect("error associating leaderboard entries with Steam players");
-    println!("Associated {n} leaderboard entries with players.");
+    sort_candidates(candidates);
+}
+
+/// How long a resolved custom profile URL is trusted before Steam is asked again.
+const VANITY_URL_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Resolves a custom profile URL, asking Steam only for URLs not resolved within `VANITY_URL_TTL`.
+async fn resolve_vanity_url(
+    db: &LeaderboardDatabase,
+    steam: &Steam,
+    vanity_url: &str,
+) -> Result<SteamId> {
+    match db.get_vanity_url(vanity_url, SystemTime::now() - VANITY_URL_TTL) {
+        Ok(Some(steam_id)) => return Ok(steam_id),
+        Ok(None) => (),
+        Err(error) => eprintln!("Error reading cached vanity URL: {vanity_url} / {error:?}"),
+    }
+
+    let steam_id = steam.resolve_id(vanity_url).await?;
+
+    if let Err(error) = db.store_vanity_url(vanity_url, steam_id) {
+        eprintln!("Error caching vanity URL: {vanity_url} / {steam_id} / {error:?}");
+    }
+
+    Ok(steam_id)
+}
+
+fn queue_review(
+    db: &LeaderboardDatabase,
+    names_id: i32,
+    avatar_hash_id: i32,
+    candidates: &[Candidate],
+    ambiguous: bool,
+) {
+    let json = serde_json::to_value(candidates).expect("error serializing candidates");
+
+    if let Err(error) = db.queue_review(names_id, avatar_hash_id, &json, ambiguous) {
+        eprintln!("Error queueing player for review: {names_id} / {avatar_hash_id} / {error:?}");
+    }
 }
 
 async fn init() -> (LeaderboardDatabase, Steam) {
     dotenv().ok();
     tracing_subscriber::fmt::init();
 
-    let db = LeaderboardDatabase::new().expect("error connecting to databse");
-    let mut steam = Steam::new().expect("error initializing Steam client");
-
-    steam
-        .start_session()
-        .await
-        .expect("failed to fetch the session ID cookie");
+    let db = LeaderboardDatabase::new().expect("error connecting to database");
+    let steam = Steam::new().expect("error initializing Steam client");
 
     (db, steam)
 }
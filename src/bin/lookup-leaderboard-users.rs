@@ -1,21 +1,16 @@
 use dotenv::dotenv;
 use leaderboard_scraper::{
-    parse_avatar_url, scrape::scrape_steam_users, scrape_leaderboard, steam::Steam, Error, Result,
-    SteamId,
+    parse_avatar_url, scrape::HtmlScraper, scrape_leaderboard, steam::Steam, Error, ProfileId,
+    Result, SteamId,
 };
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
 
-    let mut steam = Steam::new().expect("error initializing Steam client");
+    let steam = Steam::new().expect("error initializing Steam client");
 
-    steam
-        .start_session()
-        .await
-        .expect("failed to fetch the session ID cookie");
-
-    let leaderboard = scrape_leaderboard()
+    let (leaderboard, _) = scrape_leaderboard()
         .await
         .expect("Error fetching the Line War leaderboard.");
 
@@ -27,13 +22,14 @@ async fn main() {
     }
 }
 
-async fn find_user(steam: &Steam, name: &str, avatar: &str) -> Result<u64> {
+async fn find_user(steam: &Steam, name: &str, avatar: &str) -> Result<SteamId> {
     let search_response = steam
         .search_users(name, 1)
         .await
         .expect("error searching for Steam users");
     let avatar_hash = parse_avatar_url(avatar).ok_or(Error::UserNotFound)?;
-    let users = scrape_steam_users(search_response.html.as_str(), |user| {
+    let scraper = HtmlScraper::from_env()?;
+    let (users, report) = scraper.scrape_steam_users(search_response.html.as_str(), |user| {
         let user_hash = parse_avatar_url(user.avatar.as_str())?;
 
         if user.name == name && user_hash == avatar_hash {
@@ -43,18 +39,26 @@ async fn find_user(steam: &Steam, name: &str, avatar: &str) -> Result<u64> {
         }
     })?;
 
-    for id in users.into_iter() {
-        let id = resolve_id(&steam, id).await?;
+    for skipped in report.rows_skipped.iter() {
+        eprintln!(
+            "skipped Steam search row {} for {name:?}: {}",
+            skipped.row, skipped.reason
+        );
+    }
 
-        return Ok(id);
+    if !users.is_empty() && steam.is_shared_avatar(avatar_hash) {
+        return Err(Error::AmbiguousMatch(users.len()));
     }
 
-    Err(Error::UserNotFound)
+    match users.into_iter().next() {
+        Some(id) => resolve_id(steam, id).await,
+        None => Err(Error::UserNotFound),
+    }
 }
 
-async fn resolve_id(steam: &Steam, id: SteamId) -> Result<u64> {
+async fn resolve_id(steam: &Steam, id: ProfileId) -> Result<SteamId> {
     match id {
-        SteamId::Id(value) => Ok(value),
-        SteamId::Url(value) => steam.resolve_id(value.as_str()).await,
+        ProfileId::Id(value) => Ok(value),
+        ProfileId::Url(value) => steam.resolve_id(value.as_str()).await,
     }
 }
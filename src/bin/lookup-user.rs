@@ -1,7 +1,7 @@
 use clap::Parser;
 use dotenv::dotenv;
 use leaderboard_scraper::{
-    parse_avatar_url, scrape::scrape_steam_users, steam::Steam, Result, SteamId,
+    parse_avatar_url, scrape::HtmlScraper, steam::Steam, ProfileId, Result, SteamId,
 };
 use tracing::error;
 
@@ -18,14 +18,10 @@ async fn main() {
     tracing_subscriber::fmt::init();
 
     let args = Args::parse();
-    let mut steam = Steam::new().expect("error initializing Steam client");
+    let steam = Steam::new().expect("error initializing Steam client");
+    let scraper = HtmlScraper::from_env().expect("error in scraper config");
     let mut page = 0;
 
-        steam
-            .start_session()
-            .await
-            .expect("failed to fetch the session ID cookie");
-
     loop {
         eprintln!("page {page}");
 
@@ -41,7 +37,7 @@ async fn main() {
             return;
         }
 
-        let users = scrape_steam_users(search_response.html.as_str(), |user| {
+        let (users, report) = scraper.scrape_steam_users(search_response.html.as_str(), |user| {
             let hash = parse_avatar_url(user.avatar.as_str())?;
 
             if user.name == args.name && hash == args.avatar {
@@ -52,6 +48,20 @@ async fn main() {
         })
         .expect("error parsing users");
 
+        eprintln!(
+            "{} users parsed, {} skipped",
+            report.rows_parsed,
+            report.rows_skipped.len()
+        );
+
+        if !users.is_empty() && steam.is_shared_avatar(args.avatar.as_str()) {
+            eprintln!(
+                "ambiguous: the avatar is shared, so {} matches cannot be told apart",
+                users.len()
+            );
+            return;
+        }
+
         for id in users.into_iter() {
             match resolve_id(&steam, id).await {
                 Ok(value) => {
@@ -64,9 +74,9 @@ async fn main() {
     }
 }
 
-async fn resolve_id(steam: &Steam, id: SteamId) -> Result<u64> {
+async fn resolve_id(steam: &Steam, id: ProfileId) -> Result<SteamId> {
     match id {
-        SteamId::Id(value) => Ok(value),
-        SteamId::Url(value) => steam.resolve_id(value.as_str()).await,
+        ProfileId::Id(value) => Ok(value),
+        ProfileId::Url(value) => steam.resolve_id(value.as_str()).await,
     }
 }
@@ -0,0 +1,63 @@
+use clap::Parser;
+use dotenv::dotenv;
+use leaderboard_db::{
+    retention::{RetentionPolicy, DEFAULT_RETENTION_POLICY},
+    LeaderboardDatabase,
+};
+use std::time::SystemTime;
+use tracing::info;
+
+/// Downsamples old scrapes, keeping each player's first, last and peak appearances
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Comma separated INTERVAL:AGE tiers, with intervals and ages in hours, days or weeks
+    #[clap(short, long, default_value = DEFAULT_RETENTION_POLICY)]
+    policy: RetentionPolicy,
+    /// Report the scrapes that would be pruned without deleting them
+    #[clap(short = 'n', long)]
+    dry_run: bool,
+}
+
+fn main() {
+    dotenv().ok();
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    let db = LeaderboardDatabase::new().expect("error connecting to database");
+    let scrapes = db.get_scrapes().expect("error querying scrapes");
+    let protected = db
+        .get_protected_scrapes()
+        .expect("error querying protected scrapes");
+    let pruned = args
+        .policy
+        .select_pruned(scrapes.as_slice(), &protected, SystemTime::now());
+
+    info!("Applying retention policy {}.", args.policy);
+
+    if args.dry_run {
+        for scrape_id in pruned.iter() {
+            println!("Would prune scrape {scrape_id}.");
+        }
+
+        println!(
+            "Would prune {} of {} scrapes, keeping {} protected.",
+            pruned.len(),
+            scrapes.len(),
+            protected.len()
+        );
+    } else {
+        let report = db
+            .prune_scrapes(pruned.as_slice())
+            .expect("error pruning scrapes");
+
+        println!(
+            "Pruned {} of {} scrapes, removing {} rows and {} pages and trimming {} rows.",
+            report.scrapes_removed,
+            scrapes.len(),
+            report.rows_removed,
+            report.pages_removed,
+            report.rows_trimmed
+        );
+    }
+}
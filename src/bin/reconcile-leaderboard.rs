@@ -0,0 +1,90 @@
+use clap::Parser;
+use dotenv::dotenv;
+use leaderboard_scraper::{
+    config::ScrapeConfig,
+    default_transport,
+    scrape::{HtmlScraper, ParseMode},
+    scrape_leaderboard_with,
+    source::{fetch_steam_leaderboard, reconcile},
+    Steam, DEFAULT_CONCURRENCY,
+};
+use std::path::PathBuf;
+
+/// Fetches the HTML and Steam leaderboards and reports where they disagree
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Largest rating difference still counted as equal
+    #[clap(short, long, default_value_t = 0.01)]
+    tolerance: f64,
+    /// Print the full report as JSON
+    #[clap(short, long)]
+    json: bool,
+    /// Selector and column mapping config file, overriding SCRAPER_CONFIG
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    let config = match args.config.as_ref() {
+        Some(path) => ScrapeConfig::load(path),
+        None => ScrapeConfig::from_env(),
+    }
+    .expect("error loading scraper config");
+    let scraper = HtmlScraper::new(&config).expect("error in scraper config");
+    let transport = default_transport().expect("error initializing HTTP client");
+    let steam = Steam::new()
+        .expect("error initializing Steam client")
+        .with_endpoints(config.endpoints.clone());
+
+    let (html, _) = scrape_leaderboard_with(
+        &transport,
+        &config.endpoints,
+        &scraper,
+        DEFAULT_CONCURRENCY,
+        ParseMode::Lenient,
+    )
+    .await
+    .expect("Error fetching the Line War leaderboard.");
+    let (steam, _) = fetch_steam_leaderboard(&steam, &config.steam_leaderboard)
+        .await
+        .expect("Error fetching the Steam leaderboard.");
+    let report = reconcile(html.as_slice(), steam.as_slice(), args.tolerance);
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&report).expect("error serializing report");
+        println!("{json}");
+    } else {
+        for name in report.html_only.iter() {
+            println!("Only on linewar.com: {name}");
+        }
+
+        for name in report.steam_only.iter() {
+            println!("Only on Steam: {name}");
+        }
+
+        for difference in report.differences.iter() {
+            println!(
+                "Differs: {} (rank {} / {}, rating {:.2} / {:.2})",
+                difference.name,
+                difference.html_rank,
+                difference.steam_rank,
+                difference.html_rating,
+                difference.steam_rating
+            );
+        }
+
+        println!(
+            "Matched {} of {} linewar.com and {} Steam entries; {} differ.",
+            report.matched,
+            html.len(),
+            steam.len(),
+            report.differences.len()
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use clap::Parser;
+use dotenv::dotenv;
+use leaderboard_db::{
+    models::{NewEntry, ScrapeParse},
+    LeaderboardDatabase,
+};
+use leaderboard_scraper::{
+    archive::ArchivedPage,
+    config::ScrapeConfig,
+    scrape::{HtmlScraper, ParseMode, ScrapeReport},
+    Entry,
+};
+use std::path::PathBuf;
+use tracing::{error, info};
+
+/// Re-runs the current parser over archived leaderboard pages and stores the corrected rows
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Scrapes to re-parse; every scrape with archived pages by default
+    #[clap(short, long)]
+    scrape: Vec<i32>,
+    /// Only store rows for scrapes that have none, instead of rewriting existing rows
+    #[clap(short, long)]
+    append: bool,
+    /// Fail the run when a re-parse is refused, instead of skipping that scrape
+    #[clap(long)]
+    strict: bool,
+    /// Largest share of skipped rows tolerated before a scrape's rows are replaced
+    #[clap(long, default_value_t = 0.05)]
+    max_skip_ratio: f64,
+    /// Selector and column mapping config file, overriding SCRAPER_CONFIG
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
+
+fn main() {
+    dotenv().ok();
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    let config = match args.config.as_ref() {
+        Some(path) => ScrapeConfig::load(path),
+        None => ScrapeConfig::from_env(),
+    }
+    .expect("error loading scraper config");
+    let scraper = HtmlScraper::new(&config).expect("error in scraper config");
+    let db = LeaderboardDatabase::new().expect("error connecting to database");
+    let scrapes = if args.scrape.is_empty() {
+        db.get_archived_scrapes()
+            .expect("error querying archived scrapes")
+    } else {
+        args.scrape
+    };
+    let guard = ParseMode::Strict {
+        max_skip_ratio: args.max_skip_ratio,
+    };
+    let mut n = 0;
+
+    for scrape_id in scrapes {
+        if args.append && db.count_entries(scrape_id).expect("error counting entries") > 0 {
+            info!("Skipping scrape {scrape_id}, which already has entries.");
+            continue;
+        }
+
+        let (entries, report) = match reparse(&db, &scraper, scrape_id) {
+            Ok(result) => result,
+            Err(err) => {
+                error!("Error re-parsing scrape {scrape_id}: {err}");
+                continue;
+            }
+        };
+
+        // Replacing drops the scrape's stored rows and associations, so a parse that lost
+        // rows to a selector regression must not overwrite them.
+        let check = if entries.is_empty() {
+            Err("no rows were parsed".to_string())
+        } else {
+            guard.check(&report).map_err(|err| err.to_string())
+        };
+
+        if let Err(reason) = check {
+            error!("Refusing to replace the rows of scrape {scrape_id}: {reason}");
+
+            if args.strict {
+                std::process::exit(1);
+            }
+
+            continue;
+        }
+
+        let records: Vec<NewEntry> = entries
+            .iter()
+            .map(|entry| NewEntry {
+                leaderboard_scrape_id: scrape_id,
+                rank: entry.rank,
+                avatar: entry.avatar.as_str(),
+                name: entry.name.as_str(),
+                rating: entry.rating,
+                wins: entry.wins,
+                losses: entry.losses,
+                last_scrape_id: scrape_id,
+            })
+            .collect();
+        let count = db
+            .replace_entries(scrape_id, records.as_slice())
+            .expect("failed to store entries");
+        let parse = ScrapeParse {
+            page_count: report.pages_seen.len() as i32,
+            entry_count: records.len() as i32,
+            parse_errors: report.rows_skipped.len() as i32,
+            report: serde_json::to_value(&report).expect("error serializing scrape report"),
+        };
+
+        db.store_report(scrape_id, &parse)
+            .expect("failed to store scrape report");
+        info!(
+            "Re-parsed scrape {scrape_id}: {count} records from {} pages ({} rows skipped).",
+            report.pages_seen.len(),
+            report.rows_skipped.len()
+        );
+        n += count;
+    }
+
+    let associated = db
+        .associate_leaderboard()
+        .expect("error associating leaderboard entries with Steam players");
+
+    println!("Wrote {n} records and re-associated {associated} with players.");
+}
+
+fn reparse(
+    db: &LeaderboardDatabase,
+    scraper: &HtmlScraper,
+    scrape_id: i32,
+) -> Result<(Vec<Entry>, ScrapeReport), Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+    let mut report = ScrapeReport::default();
+
+    for (page, archive) in db.get_archived_pages(scrape_id)? {
+        let html = ArchivedPage {
+            hash: archive.hash,
+            content: archive.content,
+        }
+        .html()?;
+        let (mut page_entries, page_report) = scraper.scrape_leaderboard(html.as_str())?;
+
+        report.append_page(page as u32, page_report);
+        entries.append(&mut page_entries);
+    }
+
+    Ok((entries, report))
+}
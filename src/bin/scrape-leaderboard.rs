@@ -1,31 +1,268 @@
+use clap::Parser;
 use dotenv::dotenv;
-use leaderboard_db::{models::{self, NewEntry}, LeaderboardDatabase};
-use leaderboard_scraper::scrape_leaderboard;
+use futures_util::{pin_mut, TryStreamExt};
+use leaderboard_db::{
+    models::{self, NewEntry, NewSteamLeaderboardEntry, ScrapeRun},
+    writer::ScrapeWriter,
+    LeaderboardDatabase, StorageMode,
+};
+use leaderboard_scraper::{
+    archive::ArchivedPage,
+    config::ScrapeConfig,
+    policy::{PolicyTransport, RequestPolicy},
+    scrape::{HtmlScraper, ParseMode, ScrapeReport},
+    source::{fetch_steam_leaderboard, LeaderboardSource},
+    stream_leaderboard,
+    transport::{CountingTransport, HttpTransport, StatusCounts},
+    Steam, DEFAULT_CONCURRENCY, VERSION,
+};
+use std::{path::PathBuf, sync::Arc};
+use tracing::{error, info};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Number of leaderboard pages to fetch at once
+    #[clap(short, long, default_value_t = DEFAULT_CONCURRENCY)]
+    concurrency: usize,
+    /// Number of times a transient request failure is retried
+    #[clap(short, long, default_value_t = RequestPolicy::default().max_retries)]
+    max_retries: u32,
+    /// Requests per second allowed to linewar.com
+    #[clap(short, long, default_value_t = RequestPolicy::default().requests_per_second)]
+    rate: f64,
+    /// Fail the scrape when too many rows cannot be parsed
+    #[clap(short, long)]
+    strict: bool,
+    /// Largest share of skipped rows tolerated in strict mode
+    #[clap(long, default_value_t = 0.05)]
+    max_skip_ratio: f64,
+    /// Selector and column mapping config file, overriding SCRAPER_CONFIG
+    #[clap(long)]
+    config: Option<PathBuf>,
+    /// Read the linewar.com HTML leaderboard ("html") or the Steam leaderboard API ("steam").
+    /// Steam scrapes are stored apart from the leaderboard history.
+    #[clap(long, default_value_t = LeaderboardSource::Html)]
+    source: LeaderboardSource,
+    /// Write only the rows that changed since the last scrape ("delta") or every row ("full")
+    #[clap(long, default_value_t = StorageMode::Full)]
+    storage: StorageMode,
+}
+
+/// What a run has fetched so far. Pages and entries go to the database as they arrive and
+/// are not kept here; `rows` is only known once the scrape completes.
+#[derive(Default)]
+struct Fetched {
+    records: usize,
+    rows: usize,
+    report: ScrapeReport,
+    http_statuses: StatusCounts,
+}
+
+impl Fetched {
+    /// Records the run so far, with the error that ended it, if any.
+    fn run(&self, error: Option<String>) -> ScrapeRun {
+        let report = &self.report;
+
+        ScrapeRun {
+            page_count: report.pages_seen.len() as i32,
+            http_statuses: serde_json::to_value(self.http_statuses.snapshot())
+                .expect("error serializing HTTP statuses"),
+            parse_errors: report.rows_skipped.len() as i32,
+            report: serde_json::to_value(report).expect("error serializing scrape report"),
+            error,
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
     tracing_subscriber::fmt::init();
 
-    let db = LeaderboardDatabase::new().expect("error connecting to databse");
-    let scrape = db
-        .start_scrape()
-        .expect("failed to create new scrape entry");
-    let leaderboard = scrape_leaderboard()
-        .await
-        .expect("Error fetching the Line War leaderboard.");
-    let records: Vec<NewEntry> = leaderboard.iter()
-        .map(|entry| models::NewEntry {
-            leaderboard_scrape_id: scrape.id,
-            rank: entry.rank,
-            avatar: entry.avatar.as_str(),
-            name: entry.name.as_str(),
-            rating: entry.rating,
-            wins: entry.wins,
-            losses: entry.losses,
+    let args = Args::parse();
+    let db = LeaderboardDatabase::new().unwrap_or_else(|err| {
+        error!("Error connecting to the database: {err}");
+        std::process::exit(1);
+    });
+    let started = match args.source {
+        LeaderboardSource::Html => db
+            .start_scrape(args.source.to_string().as_str(), VERSION)
+            .map(|scrape| (scrape.id, scrape.at)),
+        LeaderboardSource::Steam => db
+            .start_steam_scrape(VERSION)
+            .map(|scrape| (scrape.id, scrape.at)),
+    };
+    let (scrape_id, at) = started.unwrap_or_else(|err| {
+        error!("Error creating a new scrape entry: {err}");
+        std::process::exit(1);
+    });
+    let mut fetched = Fetched::default();
+    let result = match args.source {
+        LeaderboardSource::Html => scrape_leaderboard(&db, &args, scrape_id, &mut fetched).await,
+        LeaderboardSource::Steam => scrape_steam(&db, &args, scrape_id, &mut fetched).await,
+    };
+
+    if let Err(err) = result {
+        error!("Scrape {scrape_id} ({}) failed: {err}", args.source);
+
+        let run = fetched.run(Some(err.to_string()));
+        let recorded = match args.source {
+            LeaderboardSource::Html => db.fail_scrape(scrape_id, &run),
+            LeaderboardSource::Steam => db.fail_steam_scrape(scrape_id, &run),
+        };
+
+        if let Err(err) = recorded {
+            error!("Error recording the failed scrape {scrape_id}: {err}");
+        }
+
+        std::process::exit(1);
+    }
+
+    let report = &fetched.report;
+
+    println!(
+        "Wrote {} rows for {} records at {:?} from {} pages ({} rows skipped).",
+        fetched.rows,
+        fetched.records,
+        at,
+        report.pages_seen.len(),
+        report.rows_skipped.len()
+    );
+}
+
+/// Loads the scraper config, then writes the scrape and completes it. The pages and entries
+/// written so far are rolled back when any step fails.
+async fn scrape_leaderboard(
+    db: &LeaderboardDatabase,
+    args: &Args,
+    scrape_id: i32,
+    fetched: &mut Fetched,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(args)?;
+    let mut writer = db.write_scrape(scrape_id, args.storage)?;
+
+    scrape_html(args, &config, scrape_id, &mut writer, fetched).await?;
+    parse_mode(args).check(&fetched.report)?;
+    fetched.rows = writer.complete(&fetched.run(None))?;
+
+    Ok(())
+}
+
+/// Fetches the Steam leaderboard, whose entries all come with a Steam ID, and stores it
+/// apart from the leaderboard history.
+async fn scrape_steam(
+    db: &LeaderboardDatabase,
+    args: &Args,
+    scrape_id: i32,
+    fetched: &mut Fetched,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(args)?;
+    let http = CountingTransport::new(HttpTransport::new()?);
+    fetched.http_statuses = http.counts();
+    let transport = PolicyTransport::new(http, request_policy(args));
+    let steam = Steam::with_transport(Arc::new(transport))
+        .with_key(std::env::var("STEAM_API_KEY").ok())
+        .with_endpoints(config.endpoints.clone());
+    let (entries, report) = fetch_steam_leaderboard(&steam, &config.steam_leaderboard).await?;
+
+    fetched.report = report;
+    parse_mode(args).check(&fetched.report)?;
+
+    let records: Vec<NewSteamLeaderboardEntry> = entries
+        .iter()
+        .filter_map(|entry| {
+            Some(NewSteamLeaderboardEntry {
+                steam_leaderboard_scrape_id: scrape_id,
+                rank: entry.rank,
+                steam_id: entry.steam_id?,
+                avatar: entry.avatar.as_str(),
+                name: entry.name.as_str(),
+                rating: entry.rating,
+            })
         })
         .collect();
-    let n = db.store_entries(records.as_slice()).expect("failed to store entry");
 
-    println!("Wrote {n} records at {:?}.", scrape.at);
+    fetched.records = records.len();
+    fetched.rows = db.complete_steam_scrape(scrape_id, records.as_slice(), &fetched.run(None))?;
+
+    Ok(())
+}
+
+fn load_config(args: &Args) -> leaderboard_scraper::Result<ScrapeConfig> {
+    match args.config.as_ref() {
+        Some(path) => ScrapeConfig::load(path),
+        None => ScrapeConfig::from_env(),
+    }
+}
+
+fn parse_mode(args: &Args) -> ParseMode {
+    if args.strict {
+        ParseMode::Strict {
+            max_skip_ratio: args.max_skip_ratio,
+        }
+    } else {
+        ParseMode::Lenient
+    }
+}
+
+fn request_policy(args: &Args) -> RequestPolicy {
+    RequestPolicy {
+        max_retries: args.max_retries,
+        requests_per_second: args.rate,
+        ..RequestPolicy::default()
+    }
+}
+
+/// Fetches the HTML leaderboard and writes each page, compressed for the archive, and its
+/// entries as they arrive.
+async fn scrape_html(
+    args: &Args,
+    config: &ScrapeConfig,
+    scrape_id: i32,
+    writer: &mut ScrapeWriter<'_>,
+    fetched: &mut Fetched,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let scraper = HtmlScraper::new(config)?;
+    let http = CountingTransport::new(HttpTransport::new()?);
+    fetched.http_statuses = http.counts();
+    let transport = PolicyTransport::new(http, request_policy(args));
+    let pages = stream_leaderboard(&transport, &config.endpoints, &scraper, args.concurrency);
+
+    pin_mut!(pages);
+
+    while let Some(page) = pages.try_next().await? {
+        let archive = ArchivedPage::new(page.html.as_str())?;
+        let records: Vec<NewEntry> = page
+            .entries
+            .iter()
+            .map(|entry| NewEntry {
+                leaderboard_scrape_id: scrape_id,
+                rank: entry.rank,
+                avatar: entry.avatar.as_str(),
+                name: entry.name.as_str(),
+                rating: entry.rating,
+                wins: entry.wins,
+                losses: entry.losses,
+                last_scrape_id: scrape_id,
+            })
+            .collect();
+
+        writer.archive_page(
+            page.index as i32,
+            &models::PageArchive {
+                hash: archive.hash,
+                content: archive.content,
+            },
+        )?;
+        writer.write_entries(records.as_slice())?;
+        fetched.records += records.len();
+        fetched.report.append_page(page.index, page.report);
+        info!(
+            "Stored page {} ({} records so far).",
+            page.index, fetched.records
+        );
+    }
+
+    Ok(())
 }
@@ -0,0 +1,90 @@
+use clap::Parser;
+use dotenv::dotenv;
+use leaderboard_db::{models::SteamProfile, LeaderboardDatabase};
+use leaderboard_scraper::{
+    config::ScrapeConfig,
+    steam::{Ownership, VISIBILITY_PUBLIC},
+    Steam,
+};
+use std::time::{Duration, SystemTime};
+
+/// Refreshes the Steam profiles of associated players and, when the scraper config has Line
+/// War's app ID, snapshots the playtime of public profiles. Meant to run periodically;
+/// profiles synced within `max_age` are skipped, so frequent runs only fetch what is due.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Hours before a synced profile is fetched again
+    #[clap(short, long, default_value_t = 24)]
+    max_age: u64,
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    let db = LeaderboardDatabase::new().expect("error connecting to database");
+    let config = ScrapeConfig::from_env().expect("error loading scraper config");
+    let steam = Steam::new().expect("error initializing Steam client");
+    let started = SystemTime::now();
+    let synced_before = started - Duration::from_secs(args.max_age * 60 * 60);
+
+    let steam_ids = db
+        .get_profiles_to_sync(synced_before)
+        .expect("error querying profiles to sync");
+    println!("Found {} profiles to sync.", steam_ids.len());
+
+    let profiles: Vec<_> = steam
+        .get_player_summaries(steam_ids.as_slice())
+        .await
+        .expect("error fetching player summaries")
+        .into_iter()
+        .map(|summary| SteamProfile {
+            steam_id: summary.steamid,
+            persona_name: summary.personaname,
+            avatar_url: summary.avatarfull,
+            profile_url: summary.profileurl,
+            country_code: summary.loccountrycode,
+            visibility: summary.communityvisibilitystate,
+            synced_at: started,
+        })
+        .collect();
+
+    let n = db
+        .store_profiles(profiles.as_slice())
+        .expect("error storing profiles");
+    println!("Stored {n} profiles.");
+
+    if let Some(app_id) = config.steam_leaderboard.app_id {
+        let mut n = 0;
+
+        for profile in profiles
+            .iter()
+            .filter(|profile| profile.visibility == VISIBILITY_PUBLIC)
+        {
+            match steam.get_ownership(profile.steam_id, app_id).await {
+                Ok(Ownership::Owned { playtime_minutes }) => {
+                    n += db
+                        .store_playtime(profile.steam_id, playtime_minutes as i32)
+                        .expect("error storing playtime");
+                }
+                Ok(_) => (),
+                Err(error) => {
+                    eprintln!("Error fetching playtime: {} / {error:?}", profile.steam_id)
+                }
+            }
+        }
+
+        println!("Stored {n} playtime snapshots.");
+    }
+
+    let stale = db
+        .get_stale_associations(synced_before)
+        .expect("error querying stale associations");
+
+    for (name, steam_id) in stale.iter() {
+        println!("Stale association: {name} / {steam_id}");
+    }
+}
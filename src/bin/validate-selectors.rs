@@ -0,0 +1,52 @@
+use clap::{ArgEnum, Parser};
+use leaderboard_scraper::{config::ScrapeConfig, scrape::HtmlScraper};
+use std::path::PathBuf;
+
+/// Runs the configured selectors against a saved page and prints what they extract.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Kind of page saved in the HTML file
+    #[clap(arg_enum)]
+    page: Page,
+    /// Saved HTML page
+    html: PathBuf,
+    /// Selector and column mapping config file, overriding SCRAPER_CONFIG
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
+
+#[derive(ArgEnum, Clone, Debug)]
+enum Page {
+    Leaderboard,
+    SteamUsers,
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+
+    let args = Args::parse();
+    let config = match args.config.as_ref() {
+        Some(path) => ScrapeConfig::load(path),
+        None => ScrapeConfig::from_env(),
+    }
+    .expect("error loading scraper config");
+    let scraper = HtmlScraper::new(&config).expect("error in scraper config");
+    let html = std::fs::read_to_string(args.html).expect("error reading HTML file");
+    let json = match args.page {
+        Page::Leaderboard => scraper
+            .inspect_leaderboard(html.as_str())
+            .and_then(|inspection| serde_json::to_string_pretty(&inspection).map_err(Into::into)),
+        Page::SteamUsers => scraper
+            .inspect_steam_users(html.as_str())
+            .and_then(|inspection| serde_json::to_string_pretty(&inspection).map_err(Into::into)),
+    };
+
+    match json {
+        Ok(json) => println!("{json}"),
+        Err(error) => {
+            eprintln!("Selectors failed: {error}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! A validated Steam account ID that parses and formats the SteamID64, SteamID2
+//! (`STEAM_0:X:Y`) and SteamID3 (`[U:1:Z]`) notations.
+
+#![cfg_attr(feature = "diesel", allow(non_local_definitions))]
+
+#[cfg(feature = "diesel")]
+#[macro_use]
+extern crate diesel;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "diesel")]
+mod sql;
+
+/// SteamID64 of account number zero: public universe, individual account, desktop instance.
+const INDIVIDUAL_BASE: u64 = 0x0110_0001_0000_0000;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("invalid Steam ID: {0}")]
+    InvalidFormat(String),
+    #[error("{0} is not the Steam ID of a public individual account")]
+    NotIndividual(u64),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The Steam ID of a public individual account, which is the only kind that plays Line War.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "diesel", derive(AsExpression, FromSqlRow))]
+#[cfg_attr(feature = "diesel", sql_type = "diesel::sql_types::BigInt")]
+pub struct SteamId(u64);
+
+impl SteamId {
+    pub fn new(id64: u64) -> Result<Self> {
+        if id64 >> 32 == INDIVIDUAL_BASE >> 32 {
+            Ok(Self(id64))
+        } else {
+            Err(Error::NotIndividual(id64))
+        }
+    }
+
+    pub fn from_account_id(account_id: u32) -> Self {
+        Self(INDIVIDUAL_BASE | account_id as u64)
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// The low 32 bits, which number the account within its universe.
+    pub fn account_id(&self) -> u32 {
+        self.0 as u32
+    }
+
+    /// Formats the ID as `STEAM_0:X:Y`.
+    pub fn steam2(&self) -> String {
+        let account_id = self.account_id();
+
+        format!("STEAM_0:{}:{}", account_id & 1, account_id >> 1)
+    }
+
+    /// Formats the ID as `[U:1:Z]`.
+    pub fn steam3(&self) -> String {
+        format!("[U:1:{}]", self.account_id())
+    }
+
+    fn parse_steam2(value: &str) -> Option<Self> {
+        // Older games report the public universe as 0 rather than 1.
+        let rest = value
+            .strip_prefix("STEAM_0:")
+            .or_else(|| value.strip_prefix("STEAM_1:"))?;
+        let (low_bit, high_bits) = rest.split_once(':')?;
+        let low_bit: u32 = low_bit.parse().ok().filter(|bit| *bit <= 1)?;
+        let high_bits: u32 = high_bits
+            .parse()
+            .ok()
+            .filter(|bits| *bits <= u32::MAX >> 1)?;
+
+        Some(Self::from_account_id(high_bits << 1 | low_bit))
+    }
+
+    fn parse_steam3(value: &str) -> Option<Self> {
+        let account_id = value.strip_prefix("[U:1:")?.strip_suffix(']')?;
+
+        account_id.parse().ok().map(Self::from_account_id)
+    }
+}
+
+impl fmt::Display for SteamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for SteamId {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+
+        if let Ok(id64) = value.parse() {
+            return Self::new(id64);
+        }
+
+        Self::parse_steam2(value)
+            .or_else(|| Self::parse_steam3(value))
+            .ok_or_else(|| Error::InvalidFormat(value.to_string()))
+    }
+}
+
+impl TryFrom<u64> for SteamId {
+    type Error = Error;
+
+    fn try_from(id64: u64) -> Result<Self> {
+        Self::new(id64)
+    }
+}
+
+impl From<SteamId> for u64 {
+    fn from(steam_id: SteamId) -> Self {
+        steam_id.0
+    }
+}
+
+/// Serializes as the SteamID64 number, matching the JSON the server already returns.
+impl Serialize for SteamId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+/// Deserializes a SteamID64 number or a string in any supported notation.
+impl<'de> Deserialize<'de> for SteamId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = SteamId;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a SteamID64, SteamID2 or SteamID3")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<SteamId, E> {
+                SteamId::new(value).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<SteamId, E> {
+                SteamId::new(value as u64).map_err(E::custom)
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<SteamId, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Error, SteamId};
+
+    const ID64: u64 = 76561198020520825;
+
+    #[test]
+    fn test_parse_formats() {
+        let steam_id = SteamId::new(ID64).unwrap();
+
+        for text in [
+            "76561198020520825",
+            "STEAM_0:1:30127548",
+            "STEAM_1:1:30127548",
+            "[U:1:60255097]",
+            " [U:1:60255097] ",
+        ] {
+            assert_eq!(text.parse::<SteamId>(), Ok(steam_id), "{text}");
+        }
+
+        assert_eq!(steam_id.account_id(), 60255097);
+        assert_eq!(steam_id.to_string(), "76561198020520825");
+        assert_eq!(steam_id.steam2(), "STEAM_0:1:30127548");
+        assert_eq!(steam_id.steam3(), "[U:1:60255097]");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(SteamId::new(1), Err(Error::NotIndividual(1)));
+        assert!("103582791429521412".parse::<SteamId>().is_err());
+        assert!("STEAM_0:2:1".parse::<SteamId>().is_err());
+        assert!("[G:1:4]".parse::<SteamId>().is_err());
+        assert!("monjardin1".parse::<SteamId>().is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let steam_id = SteamId::new(ID64).unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&steam_id).unwrap(),
+            "76561198020520825"
+        );
+        assert_eq!(
+            serde_json::from_str::<SteamId>("76561198020520825").unwrap(),
+            steam_id
+        );
+        assert_eq!(
+            serde_json::from_str::<SteamId>("\"STEAM_0:1:30127548\"").unwrap(),
+            steam_id
+        );
+        assert!(serde_json::from_str::<SteamId>("1").is_err());
+    }
+}
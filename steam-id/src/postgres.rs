@@ -0,0 +1,27 @@
+use crate::SteamId;
+use bytes::BytesMut;
+use postgres_types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type};
+use std::error::Error;
+
+type BoxError = Box<dyn Error + Sync + Send>;
+
+/// Stored as a BIGINT, matching the Diesel mapping.
+impl ToSql for SteamId {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, BoxError> {
+        (self.as_u64() as i64).to_sql(ty, out)
+    }
+
+    accepts!(INT8);
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for SteamId {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, BoxError> {
+        let id64 = i64::from_sql(ty, raw)?;
+
+        SteamId::new(id64 as u64).map_err(Into::into)
+    }
+
+    accepts!(INT8);
+}
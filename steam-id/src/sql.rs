@@ -0,0 +1,23 @@
+use crate::SteamId;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::BigInt,
+};
+use std::io::Write;
+
+/// Stored as a BIGINT, which holds every individual SteamID64 without wrapping.
+impl ToSql<BigInt, Pg> for SteamId {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<BigInt, Pg>::to_sql(&(self.as_u64() as i64), out)
+    }
+}
+
+impl FromSql<BigInt, Pg> for SteamId {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let id64: i64 = FromSql::<BigInt, Pg>::from_sql(bytes)?;
+
+        SteamId::new(id64 as u64).map_err(Into::into)
+    }
+}